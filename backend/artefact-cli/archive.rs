@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use artefact_lib::{Artefact, JpegSource, OutputFormat, encode};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{EncoderSettings, schedule};
+
+/// Input extensions processed as archives of JPEGs
+pub const EXTENSIONS: [&str; 2] = ["zip", "cbz"];
+
+/// Output formats of the archive entries
+pub const FORMATS: [&str; 5] = ["png", "webp", "jpg", "avif", "jxl"];
+
+/// Process every JPEG entry of a ZIP/CBZ archive into a new archive, up to
+/// `jobs` at once
+///
+/// Entries keep their order, JPEGs are replaced by `format` images of the same
+/// name (with the extension changed unless writing JPEGs), everything else is
+/// copied as is. JPEGs that fail to process are copied as is too, and reported
+/// in the returned error once the archive is written. Nothing is written if
+/// two entries would end up with the same name.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    artefact: &Artefact,
+    budget: &schedule::Budget,
+    jobs: usize,
+    input: &Path,
+    output: &Path,
+    format: &str,
+    settings: EncoderSettings,
+    benchmark: bool,
+) -> Result<(), String> {
+    let mut archive = File::open(input)
+        .map_err(|e| e.to_string())
+        .and_then(|file| ZipArchive::new(file).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Cannot read {}: {e}", input.display()))?;
+
+    // JPEG entries, detected by content
+    let mut jpegs = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Cannot read entry {i} of {}: {e}", input.display()))?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Cannot read {}: {e}", entry.name()))?;
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            jpegs.push((i, entry.name().to_string(), data));
+        }
+    }
+
+    let mut processed = jpegs
+        .iter()
+        .zip(schedule::run(&jpegs, jobs, |_, (_, _, data)| {
+            schedule::decode(artefact, budget, JpegSource::Buffer(data.clone()))
+                .and_then(|(source, _reservation)| artefact.process_source(source))
+                .and_then(|img| {
+                    encode(
+                        &img,
+                        OutputFormat::from_extension(format, settings.quality, settings.effort)
+                            .expect("archive formats are output formats"),
+                    )
+                })
+        }))
+        .map(|((i, name, _), result)| (*i, name.clone(), result))
+        .peekable();
+
+    if benchmark {
+        return Ok(());
+    }
+
+    // Names of the new entries, checked before writing anything
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let original = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("Cannot read entry {i} of {}: {e}", input.display()))?
+            .name()
+            .to_string();
+        entries.push(match processed.next_if(|(j, _, _)| *j == i) {
+            Some((_, name, Ok(encoded))) => (entry_name(&name, format), original, Ok(encoded)),
+            Some((_, _, Err(e))) => (original.clone(), original, Err(Some(e))),
+            None => (original.clone(), original, Err(None)),
+        });
+    }
+    collision(
+        entries
+            .iter()
+            .map(|(name, original, _)| (name.as_str(), original.as_str())),
+    )?;
+
+    // Written next to the output and renamed once complete, so that an error
+    // leaves no truncated archive behind
+    let partial = output.with_file_name(format!(
+        ".{}.part",
+        output.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let failed = write(&mut archive, entries, &partial)
+        .and_then(|failed| {
+            std::fs::rename(&partial, output)
+                .map_err(|e| format!("Cannot write {}: {e}", output.display()))?;
+            Ok(failed)
+        })
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&partial);
+        })?;
+
+    if failed > 0 {
+        return Err(format!("{failed} JPEG entries were not processed"));
+    }
+    Ok(())
+}
+
+/// Name of a processed JPEG entry, with the extension of `format` unless
+/// writing JPEGs
+fn entry_name(name: &str, format: &str) -> String {
+    if format == "jpg" {
+        name.to_string()
+    } else {
+        Path::new(name)
+            .with_extension(format)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Error naming the first two entries written under the same name, from
+/// `(new name, original name)` pairs
+fn collision<'a>(names: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<(), String> {
+    let mut seen = HashMap::new();
+    for (name, original) in names {
+        if let Some(first) = seen.insert(name, original) {
+            return Err(format!(
+                "{first} and {original} would both be written as {name}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Write the entries of an archive to `path`, returning the number of JPEGs
+/// copied as is because they failed to process
+///
+/// Every entry is either its new name and encoded image, or its name and the
+/// processing error, if it was a JPEG, to copy it as is.
+#[allow(clippy::type_complexity)]
+fn write(
+    archive: &mut ZipArchive<File>,
+    entries: Vec<(String, String, Result<Vec<u8>, Option<String>>)>,
+    path: &Path,
+) -> Result<usize, String> {
+    let mut writer = ZipWriter::new(
+        File::create(path).map_err(|e| format!("Cannot create {}: {e}", path.display()))?,
+    );
+
+    let mut failed = 0;
+    for (i, (name, _, result)) in entries.into_iter().enumerate() {
+        match result {
+            Ok(encoded) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Stored)
+                    .last_modified_time(
+                        archive
+                            .by_index_raw(i)
+                            .ok()
+                            .and_then(|entry| entry.last_modified())
+                            .unwrap_or_default(),
+                    );
+                writer
+                    .start_file(&name, options)
+                    .map_err(|e| e.to_string())
+                    .and_then(|()| writer.write_all(&encoded).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("Cannot write {name}: {e}"))?;
+            }
+            Err(error) => {
+                if let Some(e) = error {
+                    eprintln!("Error in {name}: {e:?}, copied as is");
+                    failed += 1;
+                }
+                archive
+                    .by_index_raw(i)
+                    .and_then(|entry| writer.raw_copy_file(entry))
+                    .map_err(|e| format!("Cannot copy {name}: {e}"))?;
+            }
+        }
+    }
+
+    writer
+        .finish()
+        .map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_entries() {
+        assert_eq!(entry_name("pages/01.jpeg", "png"), "pages/01.png");
+        assert_eq!(entry_name("pages/01.jpeg", "jpg"), "pages/01.jpeg");
+    }
+
+    #[test]
+    fn rejects_colliding_names() {
+        assert!(collision([("a.png", "a.jpg"), ("b.png", "b.jpg")]).is_ok());
+        assert_eq!(
+            collision([("a.png", "a.jpg"), ("b.png", "b.jpg"), ("a.png", "a.jpeg")]),
+            Err("a.jpg and a.jpeg would both be written as a.png".to_string())
+        );
+        // A processed entry taking the name of another file
+        assert!(collision([("a.png", "a.png"), ("a.png", "a.jpg")]).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files picked when walking a directory
+pub const EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "jpe", "jfif"];
+
+/// Default naming template of the outputs
+pub const DEFAULT_NAME: &str = "{stem}.{ext}";
+
+/// Default naming template of the processed archives
+pub const DEFAULT_ARCHIVE_NAME: &str = "{stem}.artefact.{ext}";
+
+/// An input file, with its path relative to the directory or glob pattern it
+/// was found from, mirrored under `--out-dir`
+#[derive(Debug)]
+pub struct Input {
+    pub path: PathBuf,
+    pub relative: PathBuf,
+}
+
+/// Expand the inputs of the command line
+///
+/// Files are taken as is, directories are walked for JPEG files (with their
+/// subdirectories if `recursive`) and glob patterns are expanded the same way.
+/// Returns the inputs along with the patterns that could not be expanded.
+pub fn collect(patterns: &[String], recursive: bool) -> (Vec<Input>, Vec<String>) {
+    collect_with(patterns, recursive, &EXTENSIONS)
+}
+
+/// Like [`collect`], walking directories for the files with one of
+/// `extensions` instead of JPEG files
+pub fn collect_with(
+    patterns: &[String],
+    recursive: bool,
+    extensions: &[&str],
+) -> (Vec<Input>, Vec<String>) {
+    let mut inputs = Vec::new();
+    let mut errors = Vec::new();
+
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            let path = PathBuf::from(pattern);
+            if path.is_dir() {
+                if let Err(e) = walk(&path, &path, recursive, extensions, &mut inputs) {
+                    errors.push(format!("{pattern}: {e}"));
+                }
+            } else {
+                inputs.push(Input {
+                    relative: PathBuf::from(path.file_name().unwrap_or_default()),
+                    path,
+                });
+            }
+            continue;
+        }
+
+        // Outputs mirror the paths after the last directory without wildcard
+        let base = Path::new(pattern)
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+            .collect::<PathBuf>();
+        let paths = match glob::glob(pattern) {
+            Ok(paths) => paths,
+            Err(e) => {
+                errors.push(format!("{pattern}: {e}"));
+                continue;
+            }
+        };
+
+        let count = inputs.len();
+        for path in paths {
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => {
+                    errors.push(format!("{pattern}: {e}"));
+                    continue;
+                }
+            };
+            if path.is_dir() {
+                if let Err(e) = walk(&base, &path, recursive, extensions, &mut inputs) {
+                    errors.push(format!("{}: {e}", path.display()));
+                }
+            } else {
+                inputs.push(Input {
+                    relative: path.strip_prefix(&base).unwrap_or(&path).to_path_buf(),
+                    path,
+                });
+            }
+        }
+        if inputs.len() == count {
+            errors.push(format!("{pattern}: no matching file"));
+        }
+    }
+
+    (inputs, errors)
+}
+
+/// Add the files of `dir` with one of `extensions`, in name order, with their
+/// path relative to `base`
+fn walk(
+    base: &Path,
+    dir: &Path,
+    recursive: bool,
+    extensions: &[&str],
+    inputs: &mut Vec<Input>,
+) -> Result<(), String> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .and_then(|entries| entries.map(|entry| entry.map(|e| e.path())).collect())
+        .map_err(|e| e.to_string())?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                walk(base, &path, recursive, extensions, inputs)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        {
+            inputs.push(Input {
+                relative: path.strip_prefix(base).unwrap_or(&path).to_path_buf(),
+                path,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Output path of an input from a naming template, where `{stem}` is the
+/// input file name without extension and `{ext}` the output extension
+///
+/// The output is next to the input, or at the same relative place under
+/// `out_dir` if given.
+pub fn output_path(input: &Input, out_dir: Option<&Path>, template: &str, ext: &str) -> PathBuf {
+    let stem = input.path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = template.replace("{stem}", &stem).replace("{ext}", ext);
+
+    match out_dir {
+        Some(out_dir) => out_dir
+            .join(input.relative.parent().unwrap_or(Path::new("")))
+            .join(name),
+        None => input.path.with_file_name(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_outputs() {
+        let input = Input {
+            path: PathBuf::from("photos/2024/a.b.jpg"),
+            relative: PathBuf::from("2024/a.b.jpg"),
+        };
+
+        assert_eq!(
+            output_path(&input, None, DEFAULT_NAME, "png"),
+            Path::new("photos/2024/a.b.png")
+        );
+        assert_eq!(
+            output_path(
+                &input,
+                Some(Path::new("out")),
+                "{stem}_{stem}.{ext}",
+                "webp"
+            ),
+            Path::new("out/2024/a.b_a.b.webp")
+        );
+    }
+
+    #[test]
+    fn collects_inputs() {
+        let dir = std::env::temp_dir().join(format!("artefact-collect-{}", std::process::id()));
+        let file = |path: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().expect("in the directory"))
+                .and_then(|()| std::fs::write(&path, []))
+                .expect("writable temporary directory");
+        };
+        for path in ["b.jpg", "a.JPEG", "notes.txt", "sub/c.jpe"] {
+            file(path);
+        }
+        let relative = |inputs: &[Input]| {
+            inputs
+                .iter()
+                .map(|input| input.relative.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>()
+        };
+        let pattern = |path: &str| dir.join(path).to_string_lossy().to_string();
+
+        // Name order, subdirectories only if recursive
+        let (inputs, errors) = collect(&[pattern("")], false);
+        assert_eq!(relative(&inputs), ["a.JPEG", "b.jpg"]);
+        assert!(errors.is_empty());
+        let (inputs, _) = collect(&[pattern("")], true);
+        assert_eq!(relative(&inputs), ["a.JPEG", "b.jpg", "sub/c.jpe"]);
+
+        // Files are taken as is, patterns keep the path after their base
+        let (inputs, _) = collect(&[pattern("notes.txt"), pattern("*/*.jpe")], false);
+        assert_eq!(relative(&inputs), ["notes.txt", "sub/c.jpe"]);
+
+        let (inputs, errors) = collect(&[pattern("*.png")], false);
+        assert!(inputs.is_empty());
+        assert_eq!(errors.len(), 1);
+
+        std::fs::remove_dir_all(&dir).expect("temporary directory");
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use artefact_lib::{Jpeg, JpegSource, Stage, Timings, thread_count};
+
+use crate::{Args, EncoderSettings, artefact, planes};
+
+/// Time the processing of an image `--repeat` times without saving it, and
+/// print the min/median/max of every stage as a JSON line on stdout
+///
+/// Solver stages are summed over iterations and components, see
+/// [`Timings`].
+pub fn run(
+    args: &Args,
+    input: &str,
+    source: JpegSource,
+    extension: &str,
+    settings: EncoderSettings,
+) -> Result<(), String> {
+    // Read the file once, so that the disk is not part of the decode time
+    let source = match source {
+        JpegSource::File(path) => JpegSource::Buffer(
+            std::fs::read(&path).map_err(|e| format!("Cannot read {path}: {e}"))?,
+        ),
+        source => source,
+    };
+    let artefact = artefact(args).benchmark(false);
+    let repeat = args.repeat.max(1);
+
+    let mut size = (0, 0);
+    let mut runs = Vec::with_capacity(repeat);
+    for _ in 0..repeat {
+        let timings = Timings::default();
+        let start = Instant::now();
+
+        let jpeg = Jpeg::from(source.clone()).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        let decode = start.elapsed();
+        size = (jpeg.real_px_w, jpeg.real_px_h);
+
+        let (data, encode) = if planes::FORMATS.contains(&extension) {
+            let planes = artefact.process_source_planes_timed(
+                JpegSource::Coefficients(jpeg),
+                planes::resolution(extension, args.outputs.full_resolution),
+                &timings,
+            )?;
+            let start = Instant::now();
+            (
+                planes::encode(&planes, extension, args.outputs.depth)?,
+                start.elapsed(),
+            )
+        } else {
+            let img = artefact.process_source_timed(JpegSource::Coefficients(jpeg), &timings)?;
+            let start = Instant::now();
+            (settings.encode(&img, extension)?, start.elapsed())
+        };
+        let total = start.elapsed();
+        drop(data);
+
+        let mut run = vec![("decode", decode)];
+        run.extend(
+            timings
+                .stages()
+                .into_iter()
+                .map(|(stage, elapsed)| (stage.name(), elapsed)),
+        );
+        run.extend([("encode", encode), ("total", total)]);
+        runs.push(run);
+    }
+
+    let stages = runs[0]
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| {
+            let mut times = runs.iter().map(|run| run[i].1).collect::<Vec<_>>();
+            times.sort_unstable();
+            ((*name).to_string(), summary(&times))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    debug_assert_eq!(stages.len(), Stage::ALL.len() + 3);
+
+    let report = serde_json::json!({
+        "input": input,
+        "pipeline": artefact.pipeline(),
+        "threads": thread_count(),
+        "repeat": repeat,
+        "width": size.0,
+        "height": size.1,
+        "stages": stages,
+    });
+    println!("{report}");
+    Ok(())
+}
+
+/// Min, median and max of sorted durations, in milliseconds to the
+/// microsecond
+fn summary(sorted: &[Duration]) -> serde_json::Value {
+    let ms = |duration: Duration| (duration.as_secs_f64() * 1e6).round() / 1000.0;
+    let n = sorted.len();
+    let median = if n.is_multiple_of(2) {
+        f64::midpoint(ms(sorted[n / 2 - 1]), ms(sorted[n / 2]))
+    } else {
+        ms(sorted[n / 2])
+    };
+    serde_json::json!({
+        "min": ms(sorted[0]),
+        "median": median,
+        "max": ms(sorted[n - 1]),
+    })
+}
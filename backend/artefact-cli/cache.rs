@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::Args;
+
+/// Name of the manifest of the cached outputs in each output directory
+const MANIFEST_NAME: &str = ".artefact-cache";
+
+/// Outputs recorded with the hash of their input and settings, to skip the
+/// inputs processed before with the same settings
+///
+/// Each output directory has its own manifest, one `key<TAB>file name` line
+/// per output, later lines taking precedence.
+pub struct Cache {
+    /// Process every input, still recording the outputs
+    force: bool,
+    /// Manifests read so far, by output directory
+    manifests: Mutex<HashMap<PathBuf, HashMap<String, String>>>,
+}
+
+impl Cache {
+    pub fn new(force: bool) -> Self {
+        Self {
+            force,
+            manifests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hash of the content of an input and of every setting of its output in
+    /// `format`, along with the version so that a new release reprocesses
+    pub fn key(input: &Path, args: &Args, format: &str) -> Result<String, String> {
+        let data =
+            std::fs::read(input).map_err(|e| format!("Cannot read {}: {e}", input.display()))?;
+        let settings = [
+            env!("CARGO_PKG_VERSION"),
+            format,
+            &args.solver.weight,
+            &args.solver.pweight,
+            &args.solver.iterations,
+            &args.solver.options.chroma_guide.to_string(),
+            &args.solver.options.spearate_components.to_string(),
+            &args.solver.options.pyramid.to_string(),
+            &args.solver.options.double_precision.to_string(),
+            &args.solver.options.half_precision.to_string(),
+            &args.solver.options.fast.to_string(),
+            &args.outputs.quality.to_string(),
+            &args.outputs.effort.to_string(),
+            &args.outputs.depth.to_string(),
+            &args.outputs.full_resolution.to_string(),
+            &args.diff.join(","),
+            &args.diff_gain.to_string(),
+        ]
+        .join("\t");
+
+        let mut hasher = Sha256::new();
+        hasher.update(&data);
+        hasher.update([0]);
+        hasher.update(settings.as_bytes());
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+
+    /// Whether the output exists and was recorded with the same key
+    pub fn is_fresh(&self, output: &Path, key: &str) -> bool {
+        if self.force || !output.exists() {
+            return false;
+        }
+        let (dir, name) = split(output);
+        let mut manifests = self.manifests.lock().expect("cache lock");
+        manifests
+            .entry(dir.clone())
+            .or_insert_with(|| load(&dir.join(MANIFEST_NAME)))
+            .get(&name)
+            .is_some_and(|recorded| recorded == key)
+    }
+
+    /// Record an output written from the input and settings of `key`
+    pub fn insert(&self, output: &Path, key: String) -> Result<(), String> {
+        let (dir, name) = split(output);
+        let path = dir.join(MANIFEST_NAME);
+        let mut manifests = self.manifests.lock().expect("cache lock");
+        let manifest = manifests.entry(dir).or_insert_with(|| load(&path));
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut manifest| writeln!(manifest, "{key}\t{name}"))
+            .map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+        manifest.insert(name, key);
+        Ok(())
+    }
+}
+
+/// Directory and file name of an output
+fn split(output: &Path) -> (PathBuf, String) {
+    let dir = output
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = output
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    (dir.to_path_buf(), name)
+}
+
+fn load(path: &Path) -> HashMap<String, String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (key, name) = line.split_once('\t')?;
+            Some((name.to_string(), key.to_string()))
+        })
+        .collect()
+}
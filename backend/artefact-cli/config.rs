@@ -0,0 +1,279 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::{ArgMatches, parser::ValueSource};
+use serde::Deserialize;
+
+use crate::{OutputArgs, SolverArgs, planes};
+
+// Config file options, shared by the commands that take solver settings
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConfigArgs {
+    /// TOML config file with default settings and named presets, defaults to
+    /// `~/.config/artefact/config.toml` (`%APPDATA%\artefact\config.toml` on
+    /// Windows) if it exists
+    #[arg(long = "config", value_name = "CONFIG")]
+    pub file: Option<String>,
+
+    /// Preset of the config file to use, options given on the command line
+    /// take precedence over it
+    #[arg(long)]
+    pub preset: Option<String>,
+}
+
+/// Settings of a config file, applied to the arguments not given on the
+/// command line
+///
+/// ```toml
+/// [defaults]
+/// iterations = 100
+///
+/// [presets.anime]
+/// weight = [0.5, 0.3, 0.3]
+/// pweight = 0.002
+/// format = "webp"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    /// Used by every run
+    defaults: Preset,
+    /// Selected with `--preset`, over the defaults
+    presets: BTreeMap<String, Preset>,
+}
+
+/// Solver and output settings, per channel values are either a single value
+/// or an array of three
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Preset {
+    weight: Option<Channels<f32>>,
+    pweight: Option<Channels<f32>>,
+    iterations: Option<Channels<usize>>,
+    chroma_guide: Option<f32>,
+    pyramid: Option<usize>,
+    fast: Option<bool>,
+    format: Option<String>,
+    quality: Option<u8>,
+    effort: Option<u8>,
+    depth: Option<u8>,
+}
+
+impl Preset {
+    /// Check the values that clap checks when given on the command line,
+    /// `name` being the section of the config they come from
+    fn validate(&self, name: &str) -> Result<(), String> {
+        let ranges = [
+            ("quality", self.quality, 1..=100),
+            ("effort", self.effort, 1..=10),
+        ];
+        for (field, value, range) in ranges {
+            if let Some(value) = value.filter(|value| !range.contains(value)) {
+                return Err(format!(
+                    "Invalid {field} ({value}) in {name}, expected {}-{}",
+                    range.start(),
+                    range.end()
+                ));
+            }
+        }
+        if let Some(depth) = self.depth.filter(|depth| !planes::DEPTHS.contains(depth)) {
+            return Err(format!(
+                "Invalid depth ({depth}) in {name}, expected 8, 16 or 32"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Channels<T> {
+    ForAll(T),
+    ForEach([T; 3]),
+}
+
+impl<T: ToString> Channels<T> {
+    /// Comma separated values, as given on the command line
+    fn to_arg(&self) -> String {
+        match self {
+            Self::ForAll(value) => value.to_string(),
+            Self::ForEach(values) => values
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+/// Default location of the config file: `$XDG_CONFIG_HOME/artefact/config.toml`,
+/// `~/.config/artefact/config.toml`, or `%APPDATA%\artefact\config.toml` on
+/// Windows
+fn default_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    dir.map(|dir| dir.join("artefact").join("config.toml"))
+}
+
+/// Fill the arguments not given on the command line from the `--preset` of
+/// the config file, then from its defaults
+///
+/// The config file is `--config`, or the one at the default location if any.
+/// The output settings are left alone for the commands without `outputs`.
+pub fn apply(
+    args: &ConfigArgs,
+    solver: &mut SolverArgs,
+    outputs: Option<&mut OutputArgs>,
+    matches: &ArgMatches,
+) -> Result<(), String> {
+    let path = match &args.file {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_path().filter(|path| path.exists()),
+    };
+    let mut config = match path {
+        Some(path) => std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|config| toml::from_str::<Config>(&config).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Cannot read config {}: {e}", path.display()))?,
+        None => Config::default(),
+    };
+
+    let preset = match &args.preset {
+        Some(name) => config.presets.remove(name).ok_or_else(|| {
+            format!(
+                "Unknown preset ({name}), available: {}",
+                config
+                    .presets
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?,
+        None => Preset::default(),
+    };
+    config.defaults.validate("the defaults")?;
+    if let Some(name) = &args.preset {
+        preset.validate(&format!("preset {name}"))?;
+    }
+
+    let given = |id: &str| {
+        matches
+            .value_source(id)
+            .is_some_and(|source| source != ValueSource::DefaultValue)
+    };
+    macro_rules! apply {
+        ($args:expr, $($field:ident $(=> $to_arg:ident)?),+) => {
+            $(
+                if !given(stringify!($field)) {
+                    if let Some(value) = preset.$field.or(config.defaults.$field) {
+                        $args.$field = value$(.$to_arg())?;
+                    }
+                }
+            )+
+        };
+    }
+    apply!(solver, weight => to_arg, pweight => to_arg, iterations => to_arg);
+    apply!(solver.options, chroma_guide, pyramid, fast);
+    if let Some(outputs) = outputs {
+        apply!(outputs, format, quality, effort, depth);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+    use crate::Cli;
+
+    const CONFIG: &str = r#"
+        [defaults]
+        iterations = 100
+        quality = 80
+
+        [presets.anime]
+        weight = [0.5, 0.3, 0.3]
+        format = "webp"
+        quality = 70
+    "#;
+
+    /// Arguments of a command line, with the config applied
+    fn parse(config: &str, args: &[&str]) -> Result<crate::Args, String> {
+        // Tests run in parallel, each config gets its own file
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "artefact-config-{}-{}.toml",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, config).expect("writable temporary directory");
+
+        let path = path.to_string_lossy().to_string();
+        let command_line = ["artefact-cli", "input.jpg", "--config", &path]
+            .into_iter()
+            .chain(args.iter().copied());
+        let matches = Cli::command()
+            .try_get_matches_from(command_line)
+            .expect("valid command line");
+        let mut args = Cli::from_arg_matches(&matches)
+            .expect("valid command line")
+            .args;
+        let result = apply(
+            &args.config,
+            &mut args.solver,
+            Some(&mut args.outputs),
+            &matches,
+        );
+
+        std::fs::remove_file(&path).expect("temporary config");
+        result.map(|()| args)
+    }
+
+    #[test]
+    fn merges_defaults_and_preset() {
+        let args = parse(CONFIG, &[]).expect("valid config");
+        assert_eq!(
+            (args.solver.iterations.as_str(), args.outputs.quality),
+            ("100", 80)
+        );
+        assert_eq!(
+            (args.solver.weight.as_str(), args.outputs.format.as_str()),
+            ("0.3", "auto")
+        );
+
+        // The preset over the defaults, the command line over both
+        let args = parse(CONFIG, &["--preset", "anime", "-i", "20"]).expect("valid config");
+        assert_eq!(args.solver.weight, "0.5,0.3,0.3");
+        assert_eq!(
+            (args.outputs.format.as_str(), args.outputs.quality),
+            ("webp", 70)
+        );
+        assert_eq!(args.solver.iterations, "20");
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let error = parse(CONFIG, &["--preset", "photo"]).expect_err("unknown preset");
+        assert!(error.contains("available: anime"));
+
+        assert!(parse("[defaults]\nweigth = 0.5\n", &[]).is_err());
+
+        // Ranges checked by clap on the command line
+        let error = parse("[presets.web]\nquality = 0\n", &["--preset", "web"])
+            .expect_err("quality out of range");
+        assert!(error.contains("preset web"));
+        let error = parse("[defaults]\ndepth = 12\n", &[]).expect_err("invalid depth");
+        assert!(error.contains("depth (12) in the defaults"));
+        assert!(parse("[defaults]\neffort = 11\n", &["--effort", "5"]).is_err());
+        assert!(parse("[defaults]\niterations = [1, 2]\n", &[]).is_err());
+    }
+}
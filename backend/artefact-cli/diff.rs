@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use artefact_lib::{
+    Artefact, Jpeg, JpegSource, PlaneResolution, bound_proximity_map, difference_map, heat_map,
+    image, plain_decode,
+};
+
+/// Companion images, see `--diff`
+pub const MODES: [&str; 3] = ["signed", "heat", "bounds"];
+
+/// Companion image of an output, `<stem>.<mode>.png` next to it
+pub fn path(output: &Path, mode: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{stem}.{mode}.png"))
+}
+
+/// Process a JPEG and write the companion images of its output for `modes`,
+/// returning the processed image
+pub fn process(
+    artefact: &Artefact,
+    source: JpegSource,
+    modes: &[String],
+    gain: f32,
+    output: &Path,
+    overwrite: bool,
+) -> Result<image::RgbImage, String> {
+    for mode in modes {
+        let path = path(output, mode);
+        if path.exists() && !overwrite {
+            return Err(format!(
+                "{} already exists, use -y to overwrite",
+                path.display()
+            ));
+        }
+    }
+
+    let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+    let (processed, planes) = artefact.process_source_with_planes(
+        JpegSource::Coefficients(jpeg.clone()),
+        PlaneResolution::Native,
+    )?;
+    let plain = if modes.iter().any(|mode| mode != "bounds") {
+        Some(plain_decode(JpegSource::Coefficients(jpeg.clone()))?)
+    } else {
+        None
+    };
+
+    for mode in modes {
+        let map = match (mode.as_str(), &plain) {
+            ("signed", Some(plain)) => difference_map(plain, &processed, gain)?,
+            ("heat", Some(plain)) => heat_map(plain, &processed, gain)?,
+            ("bounds", _) => bound_proximity_map(&jpeg, &planes)?,
+            _ => return Err(format!("Invalid difference mode ({mode})")),
+        };
+        let path = path(output, mode);
+        map.save(&path)
+            .map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+    }
+
+    Ok(processed)
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use artefact_lib::{Jpeg, JpegSource};
+
+#[derive(clap::Args, Debug)]
+pub struct DumpCoefsArgs {
+    /// The input jpeg file
+    #[arg(index = 1)]
+    input: String,
+
+    /// The output .npz file, defaults to the input file with the .npz extension
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Overwrite existing output file
+    #[arg(short = 'y', long, default_value = "false")]
+    overwrite: bool,
+}
+
+pub fn run(args: DumpCoefsArgs) {
+    let output = args.output.map_or_else(
+        || PathBuf::from(&args.input).with_extension("npz"),
+        PathBuf::from,
+    );
+
+    if output.exists() && !args.overwrite {
+        eprintln!("Output file already exists, use -y to overwrite");
+        return;
+    }
+
+    match Jpeg::from(JpegSource::File(args.input)).and_then(|jpeg| jpeg.to_npz()) {
+        Ok(npz) => std::fs::write(output, npz).expect("Cannot save output file"),
+        Err(e) => eprintln!("Error: {e:?}"),
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use artefact_lib::{Artefact, EmbeddedKind, JpegSource, embedded_jpegs};
+
+use crate::{EncoderSettings, schedule};
+
+/// Process the images embedded in a JPEG file, selected by `--image`
+///
+/// `selection` is an index, `all` to write every image to
+/// `<output>_<index>.<ext>`, or `list` to print them. Images that fail are
+/// reported in the returned error once the others are written.
+pub fn run(
+    artefact: &Artefact,
+    budget: &schedule::Budget,
+    input: &Path,
+    output: &Path,
+    selection: &str,
+    settings: EncoderSettings,
+    overwrite: bool,
+) -> Result<(), String> {
+    let data = std::fs::read(input).map_err(|e| format!("Cannot read {}: {e}", input.display()))?;
+    let jpegs = embedded_jpegs(&data);
+
+    let selected = match selection {
+        "list" => {
+            for (i, jpeg) in jpegs.iter().enumerate() {
+                let kind = match jpeg.kind {
+                    EmbeddedKind::Primary => "primary",
+                    EmbeddedKind::Appended => "appended",
+                    EmbeddedKind::Thumbnail => "thumbnail",
+                };
+                println!(
+                    "{i}: {kind} at offset {}, {} bytes",
+                    jpeg.offset,
+                    jpeg.data.len()
+                );
+            }
+            return Ok(());
+        }
+        "all" => (0..jpegs.len()).collect::<Vec<_>>(),
+        index => match index.parse::<usize>() {
+            Ok(i) if i < jpegs.len() => vec![i],
+            _ => {
+                return Err(format!(
+                    "Invalid image ({index}), expected all, list or an index below {}",
+                    jpegs.len()
+                ));
+            }
+        },
+    };
+
+    let mut failed = 0;
+    for i in selected {
+        let output = if selection == "all" {
+            let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+            let ext = output.extension().unwrap_or_default().to_string_lossy();
+            output.with_file_name(format!("{stem}_{i}.{ext}"))
+        } else {
+            output.to_path_buf()
+        };
+
+        if output.exists() && !overwrite {
+            eprintln!("{} already exists, use -y to overwrite", output.display());
+            failed += 1;
+            continue;
+        }
+
+        match schedule::decode(artefact, budget, JpegSource::Buffer(jpegs[i].data.to_vec()))
+            .and_then(|(source, _reservation)| artefact.process_source(source))
+            .and_then(|img| settings.save(&img, &output))
+        {
+            Ok(()) => {}
+            Err(e) if e == "BENCHMARK" => {}
+            Err(e) => {
+                eprintln!("Error in image {i}: {e:?}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{failed} images were not processed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use std::path::PathBuf;
+
+use artefact_lib::{JpegSource, image, psnr, ssim};
+use clap::ArgMatches;
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+use crate::{
+    SolverArgs, batch,
+    config::{self, ConfigArgs},
+    schedule,
+};
+
+/// Extensions of the lossless originals picked when walking a directory
+const EXTENSIONS: [&str; 4] = ["png", "bmp", "tif", "tiff"];
+
+/// Chroma subsamplings the originals can be compressed with
+const SUBSAMPLINGS: [&str; 3] = ["444", "422", "420"];
+
+#[derive(clap::Args, Debug)]
+pub struct EvaluateArgs {
+    /// The lossless originals (png, bmp, tiff), directories searched for
+    /// them and glob patterns
+    #[arg(index = 1, required = true)]
+    originals: Vec<String>,
+
+    /// Also search the subdirectories of the directories
+    #[arg(short, long, default_value = "false")]
+    recursive: bool,
+
+    /// Number of images processed at once, defaults to the number of cores
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Memory budget of the images processed at once in MiB, estimated from
+    /// their dimensions, defaults to half of the available memory
+    #[arg(long)]
+    memory: Option<usize>,
+
+    // Only the solver settings of the config file apply
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(flatten)]
+    solver: SolverArgs,
+
+    /// JPEG qualities the originals are compressed at, comma separated
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "30,50,75,90",
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    qualities: Vec<u8>,
+
+    /// Chroma subsamplings the originals are compressed with, comma separated
+    #[arg(long, value_delimiter = ',', default_value = "420,444", value_parser = SUBSAMPLINGS)]
+    subsampling: Vec<String>,
+
+    /// Also write the results as CSV, the means having `all` as image
+    #[arg(long)]
+    csv: Option<PathBuf>,
+}
+
+/// An original compressed with one setting
+struct Case<'a> {
+    input: &'a batch::Input,
+    quality: u8,
+    subsampling: &'a str,
+}
+
+/// Scores of the standard decode and of the output against the original
+#[derive(Debug, Clone, Copy)]
+struct Scores {
+    psnr_standard: f64,
+    psnr_artefact: f64,
+    ssim_standard: f64,
+    ssim_artefact: f64,
+}
+
+impl Scores {
+    fn mean(scores: &[Self]) -> Self {
+        let n = scores.len().max(1) as f64;
+        let mean = |score: fn(&Self) -> f64| scores.iter().map(score).sum::<f64>() / n;
+        Self {
+            psnr_standard: mean(|s| s.psnr_standard),
+            psnr_artefact: mean(|s| s.psnr_artefact),
+            ssim_standard: mean(|s| s.ssim_standard),
+            ssim_artefact: mean(|s| s.ssim_artefact),
+        }
+    }
+}
+
+/// A line of the report
+struct Row {
+    image: String,
+    quality: String,
+    subsampling: String,
+    scores: Scores,
+}
+
+/// Compress the originals at every quality and subsampling, process them and
+/// report the gain of the outputs over a standard decode
+pub fn run(mut evaluate: EvaluateArgs, matches: &ArgMatches) {
+    if let Err(e) = config::apply(&evaluate.config, &mut evaluate.solver, None, matches) {
+        eprintln!("Error: {e}");
+        std::process::exit(2);
+    }
+
+    let (inputs, mut failures) =
+        batch::collect_with(&evaluate.originals, evaluate.recursive, &EXTENSIONS);
+    for failure in &failures {
+        eprintln!("Error: {failure}");
+    }
+
+    let (qualities, subsamplings) = (&evaluate.qualities, &evaluate.subsampling);
+    let cases = inputs
+        .iter()
+        .flat_map(|input| {
+            qualities.iter().flat_map(move |&quality| {
+                subsamplings.iter().map(move |subsampling| Case {
+                    input,
+                    quality,
+                    subsampling,
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let artefact = evaluate.solver.artefact();
+    let jobs = evaluate.jobs.unwrap_or_else(schedule::default_jobs);
+    let budget = schedule::Budget::with_memory(evaluate.memory);
+
+    let results = schedule::run(&cases, jobs, |i, case| {
+        eprintln!(
+            "[{}/{}] {} q{} {}",
+            i + 1,
+            cases.len(),
+            case.input.path.display(),
+            case.quality,
+            case.subsampling
+        );
+        let result = score(&artefact, &budget, case);
+        if let Err(e) = &result {
+            eprintln!("Error in {}: {e}", case.input.path.display());
+        }
+        result
+    });
+
+    let mut rows = Vec::new();
+    let mut scored = Vec::new();
+    for (case, result) in cases.iter().zip(results) {
+        match result {
+            Ok(scores) => {
+                rows.push(Row {
+                    image: case.input.relative.display().to_string(),
+                    quality: case.quality.to_string(),
+                    subsampling: case.subsampling.to_string(),
+                    scores,
+                });
+                scored.push((case, scores));
+            }
+            Err(e) => failures.push(format!(
+                "{} q{} {}: {e}",
+                case.input.path.display(),
+                case.quality,
+                case.subsampling
+            )),
+        }
+    }
+
+    // Means per setting, then overall
+    for &quality in qualities {
+        for subsampling in subsamplings {
+            let scores = scored
+                .iter()
+                .filter(|(case, _)| case.quality == quality && case.subsampling == subsampling)
+                .map(|&(_, scores)| scores)
+                .collect::<Vec<_>>();
+            if !scores.is_empty() {
+                rows.push(Row {
+                    image: "all".to_string(),
+                    quality: quality.to_string(),
+                    subsampling: subsampling.clone(),
+                    scores: Scores::mean(&scores),
+                });
+            }
+        }
+    }
+    if !scored.is_empty() {
+        rows.push(Row {
+            image: "all".to_string(),
+            quality: "all".to_string(),
+            subsampling: "all".to_string(),
+            scores: Scores::mean(&scored.iter().map(|&(_, s)| s).collect::<Vec<_>>()),
+        });
+    }
+
+    print_table(&rows);
+    if let Some(path) = &evaluate.csv
+        && let Err(e) = std::fs::write(path, csv(&rows))
+    {
+        failures.push(format!("Cannot write {}: {e}", path.display()));
+    }
+
+    if !failures.is_empty() {
+        eprintln!("{} failures:", failures.len());
+        for failure in &failures {
+            eprintln!("  {failure}");
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Compress an original, then score its standard decode and the output
+/// against it
+fn score(
+    artefact: &artefact_lib::Artefact,
+    budget: &schedule::Budget,
+    case: &Case,
+) -> Result<Scores, String> {
+    let path = &case.input.path;
+    let original = image::open(path)
+        .map_err(|e| format!("Cannot read {}: {e}", path.display()))?
+        .to_rgb8();
+    let jpeg = compress(&original, case.quality, case.subsampling)?;
+
+    // As decoded by a viewer, with fancy upsampling of the chroma
+    let standard = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Cannot decode the compressed image: {e}"))?
+        .to_rgb8();
+
+    let (source, _reservation) = schedule::decode(artefact, budget, JpegSource::Buffer(jpeg))?;
+    let output = artefact.process_source(source)?;
+
+    Ok(Scores {
+        psnr_standard: psnr(&original, &standard)?,
+        psnr_artefact: psnr(&original, &output)?,
+        ssim_standard: ssim(&original, &standard)?,
+        ssim_artefact: ssim(&original, &output)?,
+    })
+}
+
+/// Encode an image to a baseline JPEG with the IJG quality scaling
+fn compress(image: &image::RgbImage, quality: u8, subsampling: &str) -> Result<Vec<u8>, String> {
+    let size =
+        |value: u32| u16::try_from(value).map_err(|_| "Image too large for JPEG".to_string());
+    let (width, height) = (size(image.width())?, size(image.height())?);
+
+    let mut data = Vec::new();
+    let mut encoder = Encoder::new(&mut data, quality);
+    encoder.set_sampling_factor(match subsampling {
+        "444" => SamplingFactor::R_4_4_4,
+        "422" => SamplingFactor::R_4_2_2,
+        _ => SamplingFactor::R_4_2_0,
+    });
+    encoder
+        .encode(image.as_raw(), width, height, ColorType::Rgb)
+        .map_err(|e| format!("Cannot compress to JPEG: {e}"))?;
+    Ok(data)
+}
+
+fn print_table(rows: &[Row]) {
+    let image_w = rows
+        .iter()
+        .map(|row| row.image.chars().count())
+        .chain([5])
+        .max()
+        .unwrap_or(5);
+
+    println!(
+        "{:<image_w$} {:>7} {:>11} {:>9} {:>9} {:>7} {:>8} {:>8} {:>8}",
+        "image",
+        "quality",
+        "subsampling",
+        "psnr std",
+        "psnr out",
+        "gain",
+        "ssim std",
+        "ssim out",
+        "gain"
+    );
+    for row in rows {
+        let s = row.scores;
+        println!(
+            "{:<image_w$} {:>7} {:>11} {:>9.3} {:>9.3} {:>+7.3} {:>8.4} {:>8.4} {:>+8.4}",
+            row.image,
+            row.quality,
+            row.subsampling,
+            s.psnr_standard,
+            s.psnr_artefact,
+            s.psnr_artefact - s.psnr_standard,
+            s.ssim_standard,
+            s.ssim_artefact,
+            s.ssim_artefact - s.ssim_standard
+        );
+    }
+}
+
+fn csv(rows: &[Row]) -> String {
+    let field = |value: &str| {
+        if value.contains([',', '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut csv = "image,quality,subsampling,psnr_standard,psnr_artefact,psnr_gain,\
+                   ssim_standard,ssim_artefact,ssim_gain\n"
+        .to_string();
+    for row in rows {
+        let s = row.scores;
+        csv += &format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            field(&row.image),
+            field(&row.quality),
+            field(&row.subsampling),
+            s.psnr_standard,
+            s.psnr_artefact,
+            s.psnr_artefact - s.psnr_standard,
+            s.ssim_standard,
+            s.ssim_artefact,
+            s.ssim_artefact - s.ssim_standard
+        );
+    }
+    csv
+}
@@ -0,0 +1,121 @@
+use std::io::Read;
+
+use artefact_lib::{JpegInfo, jpeg_info};
+
+use crate::STDIO;
+
+#[derive(clap::Args, Debug)]
+pub struct InfoArgs {
+    /// The jpeg file, or `-` for stdin
+    #[arg(index = 1)]
+    input: String,
+
+    /// Print JSON instead of text
+    #[arg(long, default_value = "false")]
+    json: bool,
+}
+
+pub fn run(args: InfoArgs) {
+    let data = if args.input == STDIO {
+        let mut data = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut data)
+            .map(|_| data)
+            .map_err(|e| format!("cannot read stdin: {e}"))
+    } else {
+        std::fs::read(&args.input).map_err(|e| format!("cannot read {}: {e}", args.input))
+    };
+
+    let info = match data.and_then(|data| jpeg_info(&data)) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        }
+    };
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string(&to_json(&info)).expect("JSON values")
+        );
+    } else {
+        print_text(&info);
+    }
+}
+
+fn print_text(info: &JpegInfo) {
+    let yes_no = |present| if present { "yes" } else { "no" };
+
+    println!("size: {}x{}", info.width, info.height);
+    println!(
+        "encoding: {}",
+        if info.progressive {
+            "progressive"
+        } else {
+            "baseline"
+        }
+    );
+    match info.restart_interval {
+        0 => println!("restart interval: none"),
+        interval => println!("restart interval: {interval} MCUs"),
+    }
+    println!(
+        "quality: {}{} (IJG scale)",
+        if info.quality_exact { "" } else { "~" },
+        info.quality
+    );
+    println!("icc profile: {}", yes_no(info.icc_profile));
+    println!("exif: {}", yes_no(info.exif));
+
+    for (c, comp) in info.components.iter().enumerate() {
+        println!(
+            "component {c}: id {}, sampling {}x{}, quant table {}",
+            comp.id, comp.horizontal_samp, comp.vertical_samp, comp.quant_table_number
+        );
+        println!(
+            "  rounded {}x{} px ({}), {}x{} blocks ({})",
+            comp.rounded_px_w,
+            comp.rounded_px_h,
+            comp.rounded_px_count,
+            comp.block_w,
+            comp.block_h,
+            comp.block_count
+        );
+        for row in comp.quant_table.chunks(8) {
+            println!(
+                "  {}",
+                row.iter()
+                    .map(|q| format!("{q:>3}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+    }
+}
+
+fn to_json(info: &JpegInfo) -> serde_json::Value {
+    serde_json::json!({
+        "width": info.width,
+        "height": info.height,
+        "progressive": info.progressive,
+        "restart_interval": info.restart_interval,
+        "quality": info.quality,
+        "quality_exact": info.quality_exact,
+        "icc_profile": info.icc_profile,
+        "exif": info.exif,
+        "components": info.components.iter().map(|comp| serde_json::json!({
+            "id": comp.id,
+            "horizontal_samp": comp.horizontal_samp,
+            "vertical_samp": comp.vertical_samp,
+            "quant_table_number": comp.quant_table_number,
+            "quant_table": comp.quant_table.to_vec(),
+            "rounded_px_w": comp.rounded_px_w,
+            "rounded_px_h": comp.rounded_px_h,
+            "rounded_px_count": comp.rounded_px_count,
+            "block_w": comp.block_w,
+            "block_h": comp.block_h,
+            "block_count": comp.block_count,
+        })).collect::<Vec<_>>(),
+    })
+}
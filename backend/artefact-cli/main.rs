@@ -1,27 +1,237 @@
-use std::path::PathBuf;
+mod archive;
+mod batch;
+mod benchmark;
+mod cache;
+mod config;
+mod diff;
+mod dump_coefs;
+mod embedded;
+mod evaluate;
+mod info;
+mod mjpeg;
+mod planes;
+mod schedule;
+mod sequence;
+mod sweep;
+mod verify;
+mod watch;
 
-use artefact_lib::{Artefact, JpegSource, ValueCollection};
-use clap::Parser;
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use artefact_lib::{Artefact, JpegSource, OutputFormat, Precision, ValueCollection, encode, image};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that a processed image still re-encodes to the coefficients of its source JPEG
+    Verify(verify::VerifyArgs),
+
+    /// Export the quantized DCT coefficients of a JPEG to a numpy .npz archive
+    ///
+    /// The archive can be edited and processed again by passing it as the input
+    DumpCoefs(dump_coefs::DumpCoefsArgs),
+
+    /// Print the header information of a JPEG without decoding it
+    Info(info::InfoArgs),
+
+    /// Watch directories and process the JPEGs added to or changed in them
+    ///
+    /// Existing JPEGs are processed first, a record of the processed files in
+    /// the output directory keeps them from being processed again on restart
+    Watch(Box<watch::WatchArgs>),
+
+    /// Process a JPEG with every combination of lists or ranges of weight,
+    /// pweight and iterations, and write a labelled contact sheet of a crop
+    /// of the outputs to compare them at a glance. The other solver options
+    /// apply to every combination, only the decoding is shared between them
+    Sweep(sweep::SweepArgs),
+
+    /// Compress lossless originals to JPEG at several qualities and
+    /// subsamplings, process them and report the PSNR and SSIM gains over a
+    /// standard decode, per image and on average
+    Evaluate(Box<evaluate::EvaluateArgs>),
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    /// The input jpeg file
-    #[arg(index = 1)]
-    input: String,
+    /// The input jpeg files, a .npz archive from `dump-coefs`, a Motion JPEG
+    /// video (.avi, .mov, .mjpeg) to process frame by frame, or a .zip/.cbz
+    /// archive of JPEGs
+    ///
+    /// Directories are searched for JPEG files and glob patterns (`*.jpg`)
+    /// are expanded, bad files are reported at the end without stopping
+    ///
+    /// Use `-` to read a JPEG from stdin, `--format` is then required
+    #[arg(index = 1, required = true)]
+    input: Vec<String>,
 
-    /// The output file
+    /// The output file, or the output directory of the frames for a video
+    /// input (defaults to the input file without extension)
+    ///
+    /// Only for a single input, see `--out-dir` and `--name` otherwise. Use
+    /// `-` to write to stdout, the default when reading from stdin
     #[arg(short, long)]
     output: Option<String>,
 
-    /// Output format (auto, png, webp, tiff, bmp, gif)
-    #[arg(short, long, default_value = "auto")]
-    format: String,
+    /// Write the outputs to this directory instead of next to the inputs,
+    /// mirroring the directories they were found in
+    #[arg(long)]
+    out_dir: Option<String>,
+
+    /// Naming template of the outputs, `{stem}` is the input file name without
+    /// extension and `{ext}` the output extension
+    ///
+    /// Defaults to `{stem}.{ext}`, or `{stem}.artefact.{ext}` for archives
+    #[arg(long)]
+    name: Option<String>,
+
+    #[command(flatten)]
+    outputs: OutputArgs,
+
+    /// Also search the subdirectories of the input directories
+    #[arg(short, long, default_value = "false")]
+    recursive: bool,
+
+    /// Number of images processed at once, defaults to the number of cores
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Memory budget of the images processed at once in MiB, estimated from
+    /// their dimensions, defaults to half of the available memory
+    #[arg(long)]
+    memory: Option<usize>,
 
     /// Overwrite existing output file
     #[arg(short = 'y', long, default_value = "false")]
     overwrite: bool,
 
+    /// Skip the inputs whose output exists and was written from the same
+    /// input content and settings, recorded in `.artefact-cache` in the
+    /// output directories
+    ///
+    /// Applies to images and archives
+    #[arg(long, default_value = "false")]
+    cache: bool,
+
+    /// Process every input even if its output is up to date in the cache,
+    /// still recording the outputs
+    #[arg(long, default_value = "false", requires = "cache")]
+    force: bool,
+
+    #[command(flatten)]
+    config: config::ConfigArgs,
+
+    #[command(flatten)]
+    solver: SolverArgs,
+
+    /// Higher temporal weight pulls each video frame towards the previous one, reducing flicker
+    ///
+    /// Only applies to video input and `--sequence`, 0 to disable
+    #[arg(long, default_value = "0")]
+    temporal_weight: f32,
+
+    /// Start the solver of each video frame from the previous output instead of the plain decode
+    ///
+    /// Keeps static regions stable, only applies to video input and `--sequence`
+    #[arg(long, default_value = "false")]
+    warm_start: bool,
+
+    /// Process the inputs one at a time as the frames of a sequence (a burst,
+    /// or a video exported to JPEGs), in the order given and directories in
+    /// name order, with `--temporal-weight` and `--warm-start`
+    #[arg(long, default_value = "false", conflicts_with_all = ["image", "diff", "cache"])]
+    sequence: bool,
+
+    /// Image of the file to process: 0 is the primary one, followed by its EXIF
+    /// thumbnail and the images appended to it (MPO), see `--image list`
+    ///
+    /// Use `all` to process every image into <output>_<index>.<ext>
+    #[arg(long)]
+    image: Option<String>,
+
+    /// Benchmark mode, do not save output image
+    ///
+    /// For images, print the time spent in each stage as JSON on stdout, and
+    /// process the inputs one at a time
+    #[arg(short, long, default_value = "false")]
+    benchmark: bool,
+
+    /// Also write companion images of the change from the unsmoothed decode,
+    /// as `<output>.<mode>.png`, comma separated
+    ///
+    /// The baseline is the solver's starting point: the dequantized
+    /// coefficients with nearest-neighbour chroma upsampling, not the fancy
+    /// upsampling of a regular JPEG decoder, so chroma edges of subsampled
+    /// images show up in the difference too.
+    ///
+    /// signed: per channel difference around mid grey, heat: magnitude of the
+    /// difference in false colours, bounds: how close the coefficients of each
+    /// block sit to their quantization bounds. Only for image outputs
+    #[arg(long, value_delimiter = ',', value_parser = diff::MODES)]
+    diff: Vec<String>,
+
+    /// Amplification of the signed and heat differences, the heat map
+    /// saturates at a difference of 255 / gain levels
+    #[arg(long, default_value = "8")]
+    diff_gain: f32,
+
+    /// Number of runs of each image in benchmark mode, the min, median and
+    /// max times are reported
+    #[arg(long, default_value = "1")]
+    repeat: usize,
+}
+
+// Format of the outputs and settings of their encoders, shared by the
+// commands that write images
+#[derive(clap::Args, Debug, Clone)]
+struct OutputArgs {
+    /// Output format (auto, png, webp, tiff, bmp, gif, avif, jxl), jxl is lossless
+    ///
+    /// Or the YCbCr planes before RGB conversion: y4m, yuv/raw (planar, no
+    /// header), npy (float32, shape (components, height, width))
+    ///
+    /// For archives: format of the processed entries (auto, png, webp, jpg, avif, jxl)
+    #[arg(short, long, default_value = "auto")]
+    format: String,
+
+    /// Quality of lossy output (avif, and jpg entries in archives), 1-100
+    #[arg(long, default_value_t = OutputFormat::DEFAULT_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Effort of the avif encoder, 1-10, higher is slower and gives smaller files
+    #[arg(long, default_value_t = OutputFormat::DEFAULT_EFFORT, value_parser = clap::value_parser!(u8).range(1..=10))]
+    effort: u8,
+
+    /// Bits per sample of y4m (8, 16) and yuv/raw (8, 16, or 32 for floats) output
+    #[arg(long, default_value = "8", value_parser = planes::parse_depth)]
+    depth: u8,
+
+    /// Write y4m and yuv/raw chroma planes at full resolution instead of their JPEG sampling
+    #[arg(long, default_value = "false")]
+    full_resolution: bool,
+}
+
+// Settings of the solver, shared by the commands that process images
+#[derive(clap::Args, Debug, Clone)]
+struct SolverArgs {
     /// Higher second order weight give smoother transitions with less staircasing
     ///
     /// Use comma separated values for each channel
@@ -40,125 +250,577 @@ struct Args {
     #[arg(short, long, default_value = "50")]
     iterations: String,
 
+    #[command(flatten)]
+    options: SolverOptions,
+}
+
+// Settings of the solver besides its weights and iterations, which `sweep`
+// takes lists of
+#[derive(clap::Args, Debug, Clone)]
+struct SolverOptions {
+    /// Higher luma-guided weight aligns chroma edges with luma, reducing colour bleeding
+    ///
+    /// Only applies when components are optimized together, 0 to disable
+    #[arg(short, long, default_value = "0")]
+    chroma_guide: f32,
+
     /// Separately optimize components instead of all together
     #[arg(short, long, default_value = "false")]
     spearate_components: bool,
 
-    /// Benchmark mode, do not save output image
-    #[arg(short, long, default_value = "false")]
-    benchmark: bool,
+    /// Solve half resolution versions of the image first to get a better starting point
+    ///
+    /// Number of coarser levels, helps large smooth regions converge with fewer iterations. Each
+    /// level starting from a coarser one runs half of `--iterations`
+    #[arg(long, default_value = "0")]
+    pyramid: usize,
+
+    /// Fast mode, a single deblocking pass instead of the iterative solver
+    ///
+    /// Ignores weight, pweight, iterations and chroma guide
+    #[arg(long, default_value = "false")]
+    fast: bool,
+
+    /// Run the solver in double precision, slow, meant as a reference for the default single precision
+    #[arg(long, default_value = "false")]
+    double_precision: bool,
+
+    /// Store the large working buffers of the solver in half precision, ~25% less memory but slower
+    #[arg(long, default_value = "false", conflicts_with = "double_precision")]
+    half_precision: bool,
 }
 
-const POSSIBLE_FORMATS: [&str; 5] = ["png", "webp", "tiff", "bmp", "gif"];
+const POSSIBLE_FORMATS: [&str; 7] = ["png", "webp", "tiff", "bmp", "gif", "avif", "jxl"];
+
+/// Input and output path standing for stdin and stdout
+const STDIO: &str = "-";
+
+/// Settings of the lossy output encoders
+#[derive(Debug, Clone, Copy)]
+struct EncoderSettings {
+    quality: u8,
+    effort: u8,
+}
+
+impl EncoderSettings {
+    /// Encode an image in the format of a file extension
+    fn encode(self, img: &image::RgbImage, extension: &str) -> Result<Vec<u8>, String> {
+        if let Some(format) = OutputFormat::from_extension(extension, self.quality, self.effort) {
+            return encode(img, format);
+        }
+
+        let format = image::ImageFormat::from_extension(extension)
+            .ok_or_else(|| format!("Unsupported output format ({extension})"))?;
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut cursor, format)
+            .map_err(|e| format!("Can't encode image: {e}"))?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Save an image, in the format of the file extension
+    fn save(self, img: &image::RgbImage, path: &Path) -> Result<(), String> {
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        std::fs::write(path, self.encode(img, &extension)?)
+            .map_err(|e| format!("Cannot write {}: {e}", path.display()))
+    }
+}
 
 fn main() {
-    let args = Args::parse();
-
-    let output = {
-        let final_format = match (&args.format, &args.output) {
-            (f, Some(output)) if f == "auto" => {
-                let output = PathBuf::from(output);
-                output
-                    .extension()
-                    .map(|ext| ext.to_string_lossy().to_string())
-                    .unwrap_or_else(|| "png".to_string())
-            }
-            (f, None) if f == "auto" => "png".to_string(),
-            (f, _) => {
-                if !POSSIBLE_FORMATS.contains(&f.as_str()) {
-                    eprintln!(
-                        "Invalid output format ({f}), possible values: {}",
-                        POSSIBLE_FORMATS.join(", ")
-                    );
-                    return;
-                }
-                f.clone()
-            }
-        };
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-        match args.output.map(PathBuf::from).map(|p| {
-            (
-                p.extension().map(|ext| ext.to_string_lossy().to_string()),
-                p,
-            )
-        }) {
-            Some((Some(output_ext), output_path)) => {
-                if args.format != "auto" && output_ext != final_format {
-                    eprintln!("Output file extension does not match output format");
-                    return;
-                }
-                output_path
-            }
-            Some((None, output)) => output.with_extension(&final_format),
-            _ => {
-                let input_path = PathBuf::from(&args.input);
-                input_path.with_extension(&final_format)
+    match cli.command {
+        Some(Command::Verify(args)) => verify::run(args),
+        Some(Command::DumpCoefs(args)) => dump_coefs::run(args),
+        Some(Command::Info(args)) => info::run(args),
+        Some(Command::Sweep(args)) => sweep::run(args),
+        Some(Command::Evaluate(args)) => evaluate::run(
+            *args,
+            matches
+                .subcommand_matches("evaluate")
+                .expect("evaluate subcommand is matched"),
+        ),
+        Some(Command::Watch(args)) => watch::run(
+            *args,
+            matches
+                .subcommand_matches("watch")
+                .expect("watch subcommand is matched"),
+        ),
+        None => {
+            let mut args = cli.args;
+            if let Err(e) = config::apply(
+                &args.config,
+                &mut args.solver,
+                Some(&mut args.outputs),
+                &matches,
+            ) {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
             }
+            process(args);
         }
+    }
+}
+
+fn process(args: Args) {
+    let (inputs, mut failures) = batch::collect(&args.input, args.recursive);
+    for failure in &failures {
+        eprintln!("Error: {failure}");
+    }
+    let total = inputs.len() + failures.len();
+    if args.output.is_some() && total > 1 {
+        eprintln!("--output only applies to a single input, use --out-dir and --name instead");
+        std::process::exit(2);
+    }
+    if total > 1 && inputs.iter().any(|input| input.path == Path::new(STDIO)) {
+        eprintln!("stdin can only be read as the single input");
+        std::process::exit(2);
+    }
+
+    let artefact = artefact(&args);
+    let settings = EncoderSettings {
+        quality: args.outputs.quality,
+        effort: args.outputs.effort,
     };
 
-    if output.exists() && !args.overwrite && !args.benchmark {
-        eprintln!("Output file already exists, use -y to overwrite");
-        return;
-    }
-
-    match Artefact::default()
-        .source(JpegSource::File(args.input))
-        .weight({
-            let vals = args
-                .weight
-                .split(",")
-                .map(|s| {
-                    s.parse()
-                        .unwrap_or_else(|_| panic!("Invalid weight value: {}", s))
-                })
-                .collect::<Vec<f32>>();
-            match vals.len() {
-                1 => ValueCollection::ForAll(vals[0]),
-                3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
-                _ => panic!("Invalid number of weight values"),
-            }
-        })
-        .pweight({
-            let vals = args
-                .pweight
-                .split(",")
-                .map(|s| {
-                    s.parse()
-                        .unwrap_or_else(|_| panic!("Invalid pweight value: {}", s))
-                })
-                .collect::<Vec<f32>>();
-            match vals.len() {
-                1 => ValueCollection::ForAll(vals[0]),
-                3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
-                _ => panic!("Invalid number of pweight values"),
+    // Benchmarked images are timed alone
+    let jobs = if args.benchmark {
+        1
+    } else {
+        args.jobs.unwrap_or_else(schedule::default_jobs)
+    };
+    // The jobs go to the inputs of a batch, or to the images of a single archive
+    let archive_jobs = if inputs.len() > 1 { 1 } else { jobs };
+    let budget = schedule::Budget::with_memory(args.memory);
+    let cache = args.cache.then(|| cache::Cache::new(args.force));
+
+    let results = if args.sequence {
+        sequence::run(&args, &inputs, settings, &budget)
+    } else {
+        schedule::run(&inputs, jobs, |i, input| {
+            if total > 1 {
+                eprintln!("[{}/{}] {}", i + 1, inputs.len(), input.path.display());
             }
-        })
-        .iterations({
-            let vals = args
-                .iterations
-                .split(",")
-                .map(|s| {
-                    s.parse()
-                        .unwrap_or_else(|_| panic!("Invalid iterations value: {}", s))
-                })
-                .collect::<Vec<usize>>();
-            match vals.len() {
-                1 => ValueCollection::ForAll(vals[0]),
-                3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
-                _ => panic!("Invalid number of iterations values"),
+            let result = process_file(
+                &args,
+                &artefact,
+                settings,
+                &budget,
+                archive_jobs,
+                cache.as_ref(),
+                input,
+            );
+            if let Err(e) = &result {
+                eprintln!("Error in {}: {e}", input.path.display());
             }
+            result
         })
-        .benchmark(args.benchmark)
-        .separate_components(args.spearate_components)
-        .process()
+    };
+    for (input, result) in inputs.iter().zip(results) {
+        if let Err(e) = result {
+            failures.push(format!("{}: {e}", input.path.display()));
+        }
+    }
+
+    if total > 1 {
+        eprintln!("{} of {total} files processed", total - failures.len());
+        for failure in &failures {
+            eprintln!("  {failure}");
+        }
+    }
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Process a single input file into the output given by the arguments
+///
+/// Each image waits for its memory in `budget` before being solved, up to
+/// `jobs` images of an archive are processed at once. Images and archives
+/// whose output is up to date in `cache` are skipped.
+fn process_file(
+    args: &Args,
+    artefact: &Artefact,
+    settings: EncoderSettings,
+    budget: &schedule::Budget,
+    jobs: usize,
+    cache: Option<&cache::Cache>,
+    input: &batch::Input,
+) -> Result<(), String> {
+    let path = input.path.as_path();
+    let has_extension = |extensions: &[&str]| {
+        path.extension()
+            .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+    };
+    let output_path = |template: &str, ext: &str| {
+        batch::output_path(
+            input,
+            args.out_dir.as_deref().map(Path::new),
+            args.name.as_deref().unwrap_or(template),
+            ext,
+        )
+    };
+
+    if args.output.as_deref() == Some(STDIO)
+        && (has_extension(&archive::EXTENSIONS) || has_extension(&mjpeg::EXTENSIONS))
     {
-        Ok(img) => img.save(output).expect("Cannot save output image"),
-        Err(e) => {
-            if e == "BENCHMARK" {
-                return;
+        return Err("Archives and videos cannot be written to stdout".to_string());
+    }
+
+    if has_extension(&archive::EXTENSIONS) {
+        let format = if args.outputs.format == "auto" {
+            "png"
+        } else {
+            &args.outputs.format
+        };
+        if !archive::FORMATS.contains(&format) {
+            return Err(format!(
+                "Invalid output format ({format}), possible values: {}",
+                archive::FORMATS.join(", ")
+            ));
+        }
+        let output = args.output.as_ref().map_or_else(
+            || {
+                output_path(
+                    batch::DEFAULT_ARCHIVE_NAME,
+                    &path.extension().unwrap_or_default().to_string_lossy(),
+                )
+            },
+            PathBuf::from,
+        );
+        let key = match cache_key(args, cache, path, &output, format)? {
+            Cached::Fresh => return Ok(()),
+            Cached::Stale(key) => Some(key),
+            Cached::Disabled => None,
+        };
+        prepare_output(args, path, &output)?;
+        archive::run(
+            artefact,
+            budget,
+            jobs,
+            path,
+            &output,
+            format,
+            settings,
+            args.benchmark,
+        )?;
+        return match (cache, key) {
+            (Some(cache), Some(key)) => cache.insert(&output, key),
+            _ => Ok(()),
+        };
+    }
+
+    if has_extension(&mjpeg::EXTENSIONS) {
+        let format = if args.outputs.format == "auto" {
+            "png"
+        } else {
+            &args.outputs.format
+        };
+        if !POSSIBLE_FORMATS.contains(&format) {
+            return Err(format!(
+                "Invalid output format ({format}), possible values: {}",
+                POSSIBLE_FORMATS.join(", ")
+            ));
+        }
+        let output = args.output.as_ref().map_or_else(
+            || output_path(batch::DEFAULT_NAME, format).with_extension(""),
+            PathBuf::from,
+        );
+        if output == path {
+            return Err("The output directory would be the input file, use --name".to_string());
+        }
+        return mjpeg::run(
+            self::artefact(args),
+            budget,
+            path,
+            &output,
+            format,
+            settings,
+            args.overwrite,
+        );
+    }
+
+    let stdin = path == Path::new(STDIO);
+    let stdout = args
+        .output
+        .as_deref()
+        .map_or(stdin, |output| output == STDIO);
+    if stdout && args.outputs.format == "auto" {
+        return Err("--format is required when writing to stdout".to_string());
+    }
+    if stdout && !args.diff.is_empty() {
+        return Err("--diff cannot be used when writing to stdout".to_string());
+    }
+
+    let final_format = match (&args.outputs.format, &args.output) {
+        (f, Some(output)) if f == "auto" => {
+            let output = PathBuf::from(output);
+            output
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string())
+                .unwrap_or_else(|| "png".to_string())
+        }
+        (f, None) if f == "auto" => "png".to_string(),
+        (f, _) => {
+            if !POSSIBLE_FORMATS.contains(&f.as_str()) && !planes::FORMATS.contains(&f.as_str()) {
+                return Err(format!(
+                    "Invalid output format ({f}), possible values: {}, {}",
+                    POSSIBLE_FORMATS.join(", "),
+                    planes::FORMATS.join(", ")
+                ));
+            }
+            f.clone()
+        }
+    };
+
+    // None for stdout
+    let output = match args.output.as_ref().map(PathBuf::from) {
+        _ if stdout => None,
+        Some(output) => match output.extension() {
+            Some(output_ext) => {
+                if args.outputs.format != "auto" && output_ext != final_format.as_str() {
+                    return Err("Output file extension does not match output format".to_string());
+                }
+                Some(output)
             }
-            eprintln!("Error: {e:?}");
+            None => Some(output.with_extension(&final_format)),
+        },
+        None => Some(output_path(batch::DEFAULT_NAME, &final_format)),
+    };
+
+    if let Some(selection) = &args.image {
+        let Some(output) = output.filter(|_| !stdin) else {
+            return Err("--image cannot be used with stdin or stdout".to_string());
+        };
+        if let Some(parent) = output.parent().filter(|_| args.out_dir.is_some()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+        }
+        return embedded::run(
+            artefact,
+            budget,
+            path,
+            &output,
+            selection,
+            settings,
+            args.overwrite,
+        );
+    }
+
+    let key = match &output {
+        Some(output) => {
+            let key = match cache_key(args, cache, path, output, &final_format)? {
+                Cached::Fresh => return Ok(()),
+                Cached::Stale(key) => Some(key),
+                Cached::Disabled => None,
+            };
+            prepare_output(args, path, output)?;
+            key
+        }
+        None => None,
+    };
+
+    let source = if stdin {
+        let mut buffer = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buffer)
+            .map_err(|e| format!("Cannot read stdin: {e}"))?;
+        JpegSource::Buffer(buffer)
+    } else if has_extension(&["npz"]) {
+        let npz =
+            std::fs::read(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+        JpegSource::Npz(npz)
+    } else {
+        JpegSource::File(path.to_string_lossy().to_string())
+    };
+
+    let extension = output
+        .as_ref()
+        .map_or(final_format, |output| {
+            output
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .to_ascii_lowercase();
+
+    if args.benchmark {
+        return benchmark::run(args, &path.to_string_lossy(), source, &extension, settings);
+    }
+
+    let (source, _reservation) = schedule::decode(artefact, budget, source)?;
+    let result = if planes::FORMATS.contains(&extension.as_str()) {
+        if !args.diff.is_empty() {
+            return Err("--diff only applies to image outputs".to_string());
         }
+        artefact
+            .process_source_planes(
+                source,
+                planes::resolution(&extension, args.outputs.full_resolution),
+            )
+            .and_then(|planes| planes::encode(&planes, &extension, args.outputs.depth))
+    } else if let Some(output) = output.as_ref().filter(|_| !args.diff.is_empty()) {
+        diff::process(
+            artefact,
+            source,
+            &args.diff,
+            args.diff_gain,
+            output,
+            args.overwrite,
+        )
+        .and_then(|img| settings.encode(&img, &extension))
+    } else {
+        artefact
+            .process_source(source)
+            .and_then(|img| settings.encode(&img, &extension))
+    };
+
+    match (result, output) {
+        (Err(e), _) if e == "BENCHMARK" => Ok(()),
+        (Ok(data), Some(output)) => std::fs::write(&output, data)
+            .map_err(|e| format!("Cannot write {}: {e}", output.display()))
+            .and_then(|()| match (cache, key) {
+                (Some(cache), Some(key)) => cache.insert(&output, key),
+                _ => Ok(()),
+            }),
+        (Ok(data), None) => std::io::stdout()
+            .write_all(&data)
+            .and_then(|()| std::io::stdout().flush())
+            .map_err(|e| format!("Cannot write stdout: {e}")),
+        (Err(e), _) => Err(e),
+    }
+}
+
+/// State of an output in the cache
+enum Cached {
+    /// Not using the cache
+    Disabled,
+    /// Written from the same input and settings, nothing to do
+    Fresh,
+    /// To be processed, then recorded with this key
+    Stale(String),
+}
+
+/// Look an output up in the cache, unless benchmarking or reading stdin
+fn cache_key(
+    args: &Args,
+    cache: Option<&cache::Cache>,
+    input: &Path,
+    output: &Path,
+    format: &str,
+) -> Result<Cached, String> {
+    let Some(cache) = cache.filter(|_| !args.benchmark && input != Path::new(STDIO)) else {
+        return Ok(Cached::Disabled);
+    };
+    let key = cache::Cache::key(input, args, format)?;
+    if cache.is_fresh(output, &key) {
+        eprintln!("{} is up to date, skipping", output.display());
+        return Ok(Cached::Fresh);
+    }
+    Ok(Cached::Stale(key))
+}
+
+/// Check that an output file can be written, creating its directory under
+/// `--out-dir`
+fn prepare_output(args: &Args, input: &Path, output: &Path) -> Result<(), String> {
+    if output == input {
+        return Err(
+            "The output would overwrite the input file, use --name or --out-dir".to_string(),
+        );
+    }
+    if args.benchmark {
+        return Ok(());
+    }
+    if output.exists() && !args.overwrite {
+        return Err(format!(
+            "{} already exists, use -y to overwrite",
+            output.display()
+        ));
+    }
+    if let Some(parent) = output.parent().filter(|_| args.out_dir.is_some()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// Solver settings from the command line arguments
+fn artefact(args: &Args) -> Artefact {
+    args.solver
+        .artefact()
+        .benchmark(args.benchmark)
+        .temporal_weight(args.temporal_weight)
+        .warm_start(args.warm_start)
+}
+
+impl SolverArgs {
+    /// Solver with these settings
+    fn artefact(&self) -> Artefact {
+        let artefact = Artefact::default()
+            .weight({
+                let vals = self
+                    .weight
+                    .split(",")
+                    .map(|s| {
+                        s.parse()
+                            .unwrap_or_else(|_| panic!("Invalid weight value: {}", s))
+                    })
+                    .collect::<Vec<f32>>();
+                match vals.len() {
+                    1 => ValueCollection::ForAll(vals[0]),
+                    3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
+                    _ => panic!("Invalid number of weight values"),
+                }
+            })
+            .pweight({
+                let vals = self
+                    .pweight
+                    .split(",")
+                    .map(|s| {
+                        s.parse()
+                            .unwrap_or_else(|_| panic!("Invalid pweight value: {}", s))
+                    })
+                    .collect::<Vec<f32>>();
+                match vals.len() {
+                    1 => ValueCollection::ForAll(vals[0]),
+                    3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
+                    _ => panic!("Invalid number of pweight values"),
+                }
+            })
+            .iterations({
+                let vals = self
+                    .iterations
+                    .split(",")
+                    .map(|s| {
+                        s.parse()
+                            .unwrap_or_else(|_| panic!("Invalid iterations value: {}", s))
+                    })
+                    .collect::<Vec<usize>>();
+                match vals.len() {
+                    1 => ValueCollection::ForAll(vals[0]),
+                    3 => ValueCollection::ForEach([vals[0], vals[1], vals[2]]),
+                    _ => panic!("Invalid number of iterations values"),
+                }
+            });
+        self.options.apply(artefact)
+    }
+}
+
+impl SolverOptions {
+    /// Set these settings on a solver
+    fn apply(&self, artefact: Artefact) -> Artefact {
+        artefact
+            .chroma_guide(self.chroma_guide)
+            .separate_components(self.spearate_components)
+            .fast(self.fast)
+            .pyramid(self.pyramid)
+            .precision(if self.double_precision {
+                Precision::Double
+            } else if self.half_precision {
+                Precision::Half
+            } else {
+                Precision::Single
+            })
     }
 }
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use artefact_lib::{Artefact, JpegSource, Sequence, mjpeg_frames};
+
+use crate::{EncoderSettings, schedule};
+
+/// Input extensions processed as Motion JPEG videos
+pub const EXTENSIONS: [&str; 4] = ["avi", "mov", "mjpeg", "mjpg"];
+
+/// Process every frame of a Motion JPEG video into `output_dir/frame_00001.<format>`, ...
+///
+/// Frames that fail are reported in the returned error once the others are
+/// written.
+pub fn run(
+    artefact: Artefact,
+    budget: &schedule::Budget,
+    input: &Path,
+    output_dir: &Path,
+    format: &str,
+    settings: EncoderSettings,
+    overwrite: bool,
+) -> Result<(), String> {
+    let data = std::fs::read(input).map_err(|e| format!("Cannot read {}: {e}", input.display()))?;
+    let frames = mjpeg_frames(&data)?;
+
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Cannot create {}: {e}", output_dir.display()))?;
+
+    let mut sequence = Sequence::new(artefact);
+    let mut failed = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        let output = output_dir.join(format!("frame_{:05}.{format}", i + 1));
+        if output.exists() && !overwrite {
+            eprintln!("{} already exists, use -y to overwrite", output.display());
+            failed += 1;
+            continue;
+        }
+
+        eprintln!("Frame {}/{}", i + 1, frames.len());
+        match schedule::decode(
+            sequence.artefact(),
+            budget,
+            JpegSource::Buffer(frame.to_vec()),
+        )
+        .and_then(|(source, _reservation)| sequence.process(source))
+        .and_then(|img| settings.save(&img, &output))
+        {
+            Ok(()) => {}
+            Err(e) if e == "BENCHMARK" => {}
+            Err(e) => {
+                eprintln!("Error in frame {}: {e:?}", i + 1);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!(
+            "{failed} of {} frames were not processed",
+            frames.len()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use artefact_lib::{Plane, PlaneResolution, planes_to_npy};
+
+/// Output formats written from the YCbCr planes instead of an RGB image
+pub const FORMATS: [&str; 4] = ["y4m", "yuv", "raw", "npy"];
+
+/// Bits per sample of the y4m (8, 16) and yuv/raw (8, 16, 32) outputs
+pub const DEPTHS: [u8; 3] = [8, 16, 32];
+
+/// Parse a `--depth`, one of [`DEPTHS`]
+pub fn parse_depth(depth: &str) -> Result<u8, String> {
+    depth
+        .parse()
+        .ok()
+        .filter(|depth| DEPTHS.contains(depth))
+        .ok_or_else(|| "expected 8, 16 or 32".to_string())
+}
+
+/// Resolution of the planes needed by `format`
+pub fn resolution(format: &str, full_resolution: bool) -> PlaneResolution {
+    if format == "npy" || full_resolution {
+        PlaneResolution::Full
+    } else {
+        PlaneResolution::Native
+    }
+}
+
+/// Serialize planes in an output format
+///
+/// * `y4m` - single frame, full range, 8 or 16 bits
+/// * `yuv`, `raw` - planes one after the other without header, 8 or 16 bits
+///   full range, or 32 bits floats as solved (luma centered around 0)
+/// * `npy` - `float32` array of shape `(components, height, width)` as solved
+pub fn encode(planes: &[Plane], format: &str, depth: u8) -> Result<Vec<u8>, String> {
+    Ok(match (format, depth) {
+        ("npy", _) => planes_to_npy(planes)?,
+        ("y4m", 8 | 16) => {
+            let mut data = y4m_header(planes, depth)?.into_bytes();
+            data.extend(quantize(planes, depth));
+            data
+        }
+        ("yuv" | "raw", 8 | 16) => quantize(planes, depth),
+        ("yuv" | "raw", 32) => planes
+            .iter()
+            .flat_map(|plane| plane.data.iter().flat_map(|value| value.to_le_bytes()))
+            .collect(),
+        _ => return Err(format!("Unsupported depth {depth} for {format} output")),
+    })
+}
+
+/// Header and frame marker of a single frame `.y4m` file
+fn y4m_header(planes: &[Plane], depth: u8) -> Result<String, String> {
+    let luma = &planes[0];
+    let colorspace = match planes {
+        [_] => "mono",
+        [_, cb, cr] if (cb.width, cb.height) == (cr.width, cr.height) => {
+            match (
+                luma.width.div_ceil(cb.width.max(1)),
+                luma.height.div_ceil(cb.height.max(1)),
+            ) {
+                (1, 1) => "444",
+                (2, 1) => "422",
+                (2, 2) if depth == 8 => "420jpeg",
+                (2, 2) => "420",
+                _ => {
+                    return Err(
+                        "No y4m layout for this chroma sampling, use --full-resolution".to_string(),
+                    );
+                }
+            }
+        }
+        _ => return Err("Unsupported number of planes for y4m output".to_string()),
+    };
+    let colorspace = match (colorspace, depth) {
+        ("mono", 16) => "mono16".to_string(),
+        (colorspace, 16) => format!("{colorspace}p16"),
+        (colorspace, _) => colorspace.to_string(),
+    };
+
+    Ok(format!(
+        "YUV4MPEG2 W{} H{} F1:1 Ip A1:1 C{colorspace} XCOLORRANGE=FULL\nFRAME\n",
+        luma.width, luma.height
+    ))
+}
+
+/// Planes one after the other, shifted to the 0-255 range and scaled to
+/// `depth` bits, little-endian
+fn quantize(planes: &[Plane], depth: u8) -> Vec<u8> {
+    let mut data = Vec::new();
+    for value in planes.iter().flat_map(|plane| &plane.data) {
+        let value = value + 128.0;
+        if depth == 16 {
+            data.extend(((value * 257.0).round().clamp(0.0, 65535.0) as u16).to_le_bytes());
+        } else {
+            data.push(value.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane(width: u32, height: u32, value: f32) -> Plane {
+        Plane {
+            width,
+            height,
+            data: vec![value; (width * height) as usize],
+        }
+    }
+
+    #[test]
+    fn writes_y4m_headers() {
+        let header = |planes: &[Plane], depth| y4m_header(planes, depth);
+        let luma = plane(5, 3, 0.0);
+
+        assert_eq!(
+            header(std::slice::from_ref(&luma), 8).as_deref(),
+            Ok("YUV4MPEG2 W5 H3 F1:1 Ip A1:1 Cmono XCOLORRANGE=FULL\nFRAME\n")
+        );
+        let chroma = plane(3, 2, 0.0);
+        let planes = [luma.clone(), chroma.clone(), chroma];
+        assert!(header(&planes, 8).is_ok_and(|h| h.contains(" C420jpeg ")));
+        assert!(header(&planes, 16).is_ok_and(|h| h.contains(" C420p16 ")));
+
+        let chroma = plane(3, 3, 0.0);
+        let planes = [luma.clone(), chroma.clone(), chroma];
+        assert!(header(&planes, 8).is_ok_and(|h| h.contains(" C422 ")));
+        let planes = [luma.clone(), luma.clone(), luma.clone()];
+        assert!(header(&planes, 16).is_ok_and(|h| h.contains(" C444p16 ")));
+
+        // 4:1:1 has no y4m layout
+        let chroma = plane(2, 3, 0.0);
+        assert!(header(&[luma.clone(), chroma.clone(), chroma], 8).is_err());
+        assert!(header(&[luma.clone(), luma], 8).is_err());
+    }
+
+    #[test]
+    fn writes_planes() {
+        let planes = [plane(2, 2, -128.0), plane(1, 1, 0.0), plane(1, 1, 200.0)];
+
+        let y4m = encode(&planes, "y4m", 8).expect("4:2:0 planes");
+        assert!(y4m.ends_with(b"\nFRAME\n\x00\x00\x00\x00\x80\xFF"));
+
+        let raw = encode(&planes, "raw", 16).expect("16 bits");
+        assert_eq!(raw, [0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0x80, 0xFF, 0xFF]);
+
+        let floats = encode(&planes, "yuv", 32).expect("32 bits floats");
+        assert_eq!(floats.len(), 24);
+        assert_eq!(floats[..4], (-128.0f32).to_le_bytes());
+
+        assert!(encode(&planes, "y4m", 32).is_err());
+        assert!(encode(&planes, "npy", 8).is_err());
+    }
+}
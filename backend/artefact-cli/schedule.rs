@@ -0,0 +1,164 @@
+use std::sync::{
+    Condvar, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use artefact_lib::{Artefact, Jpeg, JpegSource, jpeg_info};
+
+/// Default memory budget when the available memory is unknown, in MiB
+const FALLBACK_MEMORY: usize = 4096;
+
+/// Memory shared by the images processed at once
+#[derive(Debug)]
+pub struct Budget {
+    total: usize,
+    used: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Memory reserved for one image, given back to the budget when dropped
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    budget: &'a Budget,
+    bytes: usize,
+}
+
+impl Budget {
+    /// A budget of `total` bytes
+    pub const fn new(total: usize) -> Self {
+        Self {
+            total,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// A budget of `memory` MiB, or [`default_memory`]
+    pub fn with_memory(memory: Option<usize>) -> Self {
+        Self::new(
+            memory
+                .unwrap_or_else(default_memory)
+                .saturating_mul(1024 * 1024),
+        )
+    }
+
+    /// Wait until `bytes` fit in the budget
+    ///
+    /// An image larger than the whole budget waits for every other one to
+    /// finish, then runs alone.
+    pub fn reserve(&self, bytes: usize) -> Reservation<'_> {
+        let bytes = bytes.min(self.total);
+        let mut used = self
+            .released
+            .wait_while(self.used.lock().expect("budget lock"), |used| {
+                *used + bytes > self.total
+            })
+            .expect("budget lock");
+        *used += bytes;
+
+        Reservation {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.used.lock().expect("budget lock") -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// Wait for the memory needed to process a JPEG, estimated from its headers,
+/// then decode it, see [`Artefact::memory_estimate_from_headers`]
+///
+/// `.npz` archives, and the JPEGs whose headers cannot be read on their own,
+/// are reserved for once decoded. Returns the decoded source, to process
+/// while the reservation is held.
+pub fn decode<'a>(
+    artefact: &Artefact,
+    budget: &'a Budget,
+    source: JpegSource,
+) -> Result<(JpegSource, Reservation<'a>), String> {
+    let source = match source {
+        JpegSource::File(path) => JpegSource::Buffer(
+            std::fs::read(&path).map_err(|e| format!("Failed to read JPEG file '{path}': {e}"))?,
+        ),
+        source => source,
+    };
+    let reservation = match &source {
+        JpegSource::Buffer(data) => jpeg_info(data)
+            .ok()
+            .map(|info| budget.reserve(artefact.memory_estimate_from_headers(&info))),
+        JpegSource::Coefficients(jpeg) => Some(budget.reserve(artefact.memory_estimate(jpeg))),
+        JpegSource::File(_) | JpegSource::Npz(_) => None,
+    };
+
+    let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+    let reservation =
+        reservation.unwrap_or_else(|| budget.reserve(artefact.memory_estimate(&jpeg)));
+    Ok((JpegSource::Coefficients(jpeg), reservation))
+}
+
+/// Run `job` on every item, up to `jobs` at once, and return the results in
+/// order
+///
+/// Jobs run on their own threads rather than in the rayon pool used by the
+/// solver, so that one waiting for the [`Budget`] never blocks a solver
+/// thread.
+pub fn run<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    job: impl Fn(usize, &T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let result = job(i, item);
+                    results.lock().expect("results lock")[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("results lock")
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+/// Default number of jobs, one per core
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, std::num::NonZero::get)
+}
+
+/// Default memory budget in MiB, half of the available memory as reported by
+/// `/proc/meminfo`, or 4 GiB elsewhere
+pub fn default_memory() -> usize {
+    std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix("MemAvailable:"))
+                .and_then(|kib| {
+                    kib.trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                })
+        })
+        .map_or(FALLBACK_MEMORY, |kib| kib / 1024 / 2)
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use artefact_lib::{JpegSource, Sequence};
+
+use crate::{Args, EncoderSettings, POSSIBLE_FORMATS, archive, artefact, batch, mjpeg, schedule};
+
+/// Process the inputs one after the other as the frames of a sequence, in the
+/// order they were given and directories in name order
+///
+/// Each frame is pulled towards the previous output with `--temporal-weight`
+/// and starts from it with `--warm-start`. Returns the result of each input,
+/// the frames after a failed one are processed against the last output.
+pub fn run(
+    args: &Args,
+    inputs: &[batch::Input],
+    settings: EncoderSettings,
+    budget: &schedule::Budget,
+) -> Vec<Result<(), String>> {
+    let format = if args.outputs.format == "auto" {
+        "png"
+    } else {
+        &args.outputs.format
+    };
+    if !POSSIBLE_FORMATS.contains(&format) {
+        let error = format!(
+            "Invalid output format ({format}), --sequence only writes images: {}",
+            POSSIBLE_FORMATS.join(", ")
+        );
+        return inputs.iter().map(|_| Err(error.clone())).collect();
+    }
+
+    let mut sequence = Sequence::new(artefact(args));
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            if inputs.len() > 1 {
+                eprintln!("[{}/{}] {}", i + 1, inputs.len(), input.path.display());
+            }
+            let result = frame(args, &mut sequence, format, settings, budget, input);
+            if let Err(e) = &result {
+                eprintln!("Error in {}: {e}", input.path.display());
+            }
+            result
+        })
+        .collect()
+}
+
+/// Process one input of the sequence into the output given by the arguments
+fn frame(
+    args: &Args,
+    sequence: &mut Sequence,
+    format: &str,
+    settings: EncoderSettings,
+    budget: &schedule::Budget,
+    input: &batch::Input,
+) -> Result<(), String> {
+    let path = input.path.as_path();
+    let has_extension = |extensions: &[&str]| {
+        path.extension()
+            .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+    };
+    if path == Path::new(crate::STDIO) || has_extension(&archive::EXTENSIONS) {
+        return Err("--sequence only applies to JPEG and .npz files".to_string());
+    }
+    if has_extension(&mjpeg::EXTENSIONS) {
+        return Err("Videos are already processed as a sequence".to_string());
+    }
+
+    let output = args.output.as_ref().map_or_else(
+        || {
+            batch::output_path(
+                input,
+                args.out_dir.as_deref().map(Path::new),
+                args.name.as_deref().unwrap_or(batch::DEFAULT_NAME),
+                format,
+            )
+        },
+        |output| Path::new(output).with_extension(format),
+    );
+    crate::prepare_output(args, path, &output)?;
+
+    let source = if has_extension(&["npz"]) {
+        JpegSource::Npz(
+            std::fs::read(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?,
+        )
+    } else {
+        JpegSource::File(path.to_string_lossy().to_string())
+    };
+    match schedule::decode(sequence.artefact(), budget, source)
+        .and_then(|(source, _reservation)| sequence.process(source))
+        .and_then(|img| settings.save(&img, &output))
+    {
+        Err(e) if e == "BENCHMARK" => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,442 @@
+use std::path::{Path, PathBuf};
+
+use artefact_lib::{Artefact, Jpeg, JpegSource, ValueCollection, image};
+
+use crate::{EncoderSettings, POSSIBLE_FORMATS, SolverOptions, schedule};
+
+/// Size of the default crop, centered in the image
+const DEFAULT_CROP: u32 = 256;
+
+/// Pixels of a glyph of the label font, and their scale on the sheet
+const GLYPH_W: u32 = 3;
+const GLYPH_H: u32 = 5;
+const GLYPH_SCALE: u32 = 2;
+
+/// Space around the labels and between the cells of the sheet
+const PADDING: u32 = 4;
+
+/// Most combinations of a sweep, each is a full solve and an output file
+const MAX_COMBINATIONS: usize = 1000;
+
+#[derive(clap::Args, Debug)]
+pub struct SweepArgs {
+    /// The jpeg file
+    #[arg(index = 1)]
+    input: String,
+
+    /// Second order weights to try: comma separated values and
+    /// `start:stop:step` ranges, stop included
+    #[arg(short, long, default_value = "0.3")]
+    weight: String,
+
+    /// Probability weights to try, like `--weight`
+    #[arg(short, long, default_value = "0.001")]
+    pweight: String,
+
+    /// Iteration counts to try, like `--weight`
+    #[arg(short, long, default_value = "50")]
+    iterations: String,
+
+    /// Output directory, defaults to `<input>_sweep` next to the input
+    #[arg(long)]
+    out_dir: Option<String>,
+
+    /// Region of the contact sheet as `x,y,width,height`, defaults to the
+    /// 256x256 center of the image
+    #[arg(long)]
+    crop: Option<String>,
+
+    /// Cells per row of the contact sheet, defaults to the number of weights
+    #[arg(long)]
+    columns: Option<usize>,
+
+    /// Output format of the individual outputs (png, webp, tiff, bmp, gif,
+    /// avif, jxl), the contact sheet is always png
+    #[arg(short, long, default_value = "png")]
+    format: String,
+
+    /// Number of combinations processed at once, defaults to the number of cores
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Memory budget of the combinations processed at once in MiB, defaults to
+    /// half of the available memory
+    #[arg(long)]
+    memory: Option<usize>,
+
+    /// Overwrite existing outputs
+    #[arg(short = 'y', long, default_value = "false")]
+    overwrite: bool,
+
+    // The other solver settings, the same for every combination
+    #[command(flatten)]
+    options: SolverOptions,
+}
+
+/// Settings of one output of the sweep
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Combination {
+    weight: f32,
+    pweight: f32,
+    iterations: usize,
+}
+
+impl Combination {
+    fn label(self) -> String {
+        format!("w={} p={} i={}", self.weight, self.pweight, self.iterations)
+    }
+
+    fn file_name(self, stem: &str, format: &str) -> String {
+        format!(
+            "{stem}_w{}_p{}_i{}.{format}",
+            self.weight, self.pweight, self.iterations
+        )
+    }
+}
+
+pub fn run(args: SweepArgs) {
+    if let Err(e) = sweep(&args) {
+        eprintln!("Error: {e}");
+        std::process::exit(2);
+    }
+}
+
+/// Decode the JPEG once, process every combination of the settings from its
+/// coefficients and write the outputs along with the contact sheet
+///
+/// Only the decoding is shared, each combination sets up and runs the solver
+/// from scratch.
+fn sweep(args: &SweepArgs) -> Result<(), String> {
+    if !POSSIBLE_FORMATS.contains(&args.format.as_str()) {
+        return Err(format!(
+            "Invalid output format ({}), possible values: {}",
+            args.format,
+            POSSIBLE_FORMATS.join(", ")
+        ));
+    }
+
+    let weights = unique(values(&args.weight, "weight")?.iter().map(|&w| w as f32));
+    let pweights = unique(values(&args.pweight, "pweight")?.iter().map(|&p| p as f32));
+    let iterations = unique(
+        values(&args.iterations, "iterations")?
+            .iter()
+            .map(|&i| i.round().max(0.0) as usize),
+    );
+    let combinations = combinations(&weights, &pweights, &iterations)?;
+
+    let input = Path::new(&args.input);
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let out_dir = args.out_dir.as_ref().map_or_else(
+        || input.with_file_name(format!("{stem}_sweep")),
+        PathBuf::from,
+    );
+    std::fs::create_dir_all(&out_dir)
+        .map_err(|e| format!("Cannot create {}: {e}", out_dir.display()))?;
+    let sheet_path = out_dir.join(format!("{stem}_sheet.png"));
+    let paths = combinations
+        .iter()
+        .map(|combination| out_dir.join(combination.file_name(&stem, &args.format)))
+        .collect::<Vec<_>>();
+    if !args.overwrite
+        && let Some(path) = paths.iter().chain([&sheet_path]).find(|path| path.exists())
+    {
+        return Err(format!(
+            "{} already exists, use -y to overwrite",
+            path.display()
+        ));
+    }
+
+    let jpeg = Jpeg::from(JpegSource::File(args.input.clone()))
+        .map_err(|e| format!("Failed to read JPEG: {e}"))?;
+    let crop = crop(args.crop.as_deref(), jpeg.real_px_w, jpeg.real_px_h)?;
+
+    let settings = EncoderSettings {
+        quality: artefact_lib::OutputFormat::DEFAULT_QUALITY,
+        effort: artefact_lib::OutputFormat::DEFAULT_EFFORT,
+    };
+    let budget = schedule::Budget::with_memory(args.memory);
+    let jobs = args.jobs.unwrap_or_else(schedule::default_jobs);
+
+    eprintln!(
+        "Processing {} combinations into {}",
+        combinations.len(),
+        out_dir.display()
+    );
+    let cells = schedule::run(&combinations, jobs, |i, &combination| {
+        let artefact = args
+            .options
+            .apply(Artefact::default())
+            .weight(ValueCollection::ForAll(combination.weight))
+            .pweight(ValueCollection::ForAll(combination.pweight))
+            .iterations(ValueCollection::ForAll(combination.iterations));
+        let _reservation = budget.reserve(artefact.memory_estimate(&jpeg));
+
+        let result = artefact
+            .process_source(JpegSource::Coefficients(jpeg.clone()))
+            .and_then(|img| {
+                settings.save(&img, &paths[i])?;
+                let (x, y, w, h) = crop;
+                Ok(image::imageops::crop_imm(&img, x, y, w, h).to_image())
+            });
+        match &result {
+            Ok(_) => eprintln!("{} -> {}", combination.label(), paths[i].display()),
+            Err(e) => eprintln!("Error with {}: {e}", combination.label()),
+        }
+        result.ok()
+    });
+
+    let columns = args.columns.unwrap_or(weights.len()).max(1);
+    let labels = combinations
+        .iter()
+        .map(|combination| combination.label())
+        .collect::<Vec<_>>();
+    contact_sheet(&cells, &labels, (crop.2, crop.3), columns)
+        .save(&sheet_path)
+        .map_err(|e| format!("Cannot write {}: {e}", sheet_path.display()))?;
+    eprintln!("Contact sheet: {}", sheet_path.display());
+
+    if cells.iter().any(Option::is_none) {
+        return Err("Some combinations failed".to_string());
+    }
+    Ok(())
+}
+
+/// Every combination of the settings, iterations first, then pweights
+///
+/// # Errors
+/// Returns an error if there are more than [`MAX_COMBINATIONS`].
+fn combinations(
+    weights: &[f32],
+    pweights: &[f32],
+    iterations: &[usize],
+) -> Result<Vec<Combination>, String> {
+    let count = weights.len() * pweights.len() * iterations.len();
+    if count > MAX_COMBINATIONS {
+        return Err(format!(
+            "Too many combinations ({count}), at most {MAX_COMBINATIONS}"
+        ));
+    }
+
+    Ok(iterations
+        .iter()
+        .flat_map(|&iterations| {
+            pweights.iter().flat_map(move |&pweight| {
+                weights.iter().map(move |&weight| Combination {
+                    weight,
+                    pweight,
+                    iterations,
+                })
+            })
+        })
+        .collect())
+}
+
+/// The values without their repetitions, in order
+fn unique<T: PartialEq>(values: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut unique = Vec::new();
+    for value in values {
+        if !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+
+/// Parse comma separated values and `start:stop:step` ranges, without
+/// repetitions
+fn values(spec: &str, name: &str) -> Result<Vec<f64>, String> {
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("Invalid {name} value: {value}"))
+    };
+
+    let mut values = Vec::new();
+    for item in spec.split(',') {
+        match item.split(':').collect::<Vec<_>>()[..] {
+            [value] => values.push(parse(value)?),
+            [start, stop, step] => {
+                let (start, stop, step) = (parse(start)?, parse(stop)?, parse(step)?);
+                if step <= 0.0 || stop < start {
+                    return Err(format!("Invalid {name} range: {item}"));
+                }
+                // Tolerate the rounding of the step when reaching the stop
+                let count = ((stop - start) / step + 1e-9).floor() + 1.0;
+                if count > MAX_COMBINATIONS as f64 {
+                    return Err(format!(
+                        "Too many {name} values in {item}, at most {MAX_COMBINATIONS}"
+                    ));
+                }
+                let count = count as usize;
+                values.extend((0..count).map(|k| step.mul_add(k as f64, start)));
+            }
+            _ => return Err(format!("Invalid {name} range: {item}")),
+        }
+    }
+    Ok(unique(values))
+}
+
+/// Parse `x,y,width,height` within the image, or center the default crop
+fn crop(spec: Option<&str>, width: u32, height: u32) -> Result<(u32, u32, u32, u32), String> {
+    let Some(spec) = spec else {
+        let (w, h) = (DEFAULT_CROP.min(width), DEFAULT_CROP.min(height));
+        return Ok(((width - w) / 2, (height - h) / 2, w, h));
+    };
+
+    let values = spec
+        .split(',')
+        .map(|value| value.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Invalid crop: {spec}"))?;
+    match values[..] {
+        [x, y, w, h]
+            if w > 0
+                && h > 0
+                && x.checked_add(w).is_some_and(|right| right <= width)
+                && y.checked_add(h).is_some_and(|bottom| bottom <= height) =>
+        {
+            Ok((x, y, w, h))
+        }
+        [_, _, _, _] => Err(format!(
+            "Crop {spec} is not within the {width}x{height} image"
+        )),
+        _ => Err(format!("Invalid crop, expected x,y,width,height: {spec}")),
+    }
+}
+
+/// Grid of the crops with their label above them, failed ones left grey
+fn contact_sheet(
+    cells: &[Option<image::RgbImage>],
+    labels: &[String],
+    (cell_w, cell_h): (u32, u32),
+    columns: usize,
+) -> image::RgbImage {
+    let label_h = GLYPH_H * GLYPH_SCALE + 2 * PADDING;
+    let columns = columns.min(cells.len()).max(1);
+    let rows = cells.len().div_ceil(columns);
+
+    let mut sheet = image::RgbImage::from_pixel(
+        columns as u32 * (cell_w + PADDING) + PADDING,
+        rows as u32 * (cell_h + label_h) + PADDING,
+        image::Rgb([32; 3]),
+    );
+    for (i, (cell, label)) in cells.iter().zip(labels).enumerate() {
+        let x = (i % columns) as u32 * (cell_w + PADDING) + PADDING;
+        let y = (i / columns) as u32 * (cell_h + label_h);
+
+        draw_text(&mut sheet, label, x, y + PADDING, x + cell_w);
+        match cell {
+            Some(cell) => {
+                image::imageops::replace(&mut sheet, cell, x.into(), (y + label_h).into())
+            }
+            None => image::imageops::replace(
+                &mut sheet,
+                &image::RgbImage::from_pixel(cell_w, cell_h, image::Rgb([128; 3])),
+                x.into(),
+                (y + label_h).into(),
+            ),
+        }
+    }
+    sheet
+}
+
+/// Draw a label in white, cut at `max_x`
+fn draw_text(img: &mut image::RgbImage, text: &str, x: u32, y: u32, max_x: u32) {
+    for (n, c) in text.chars().enumerate() {
+        let glyph_x = x + n as u32 * (GLYPH_W + 1) * GLYPH_SCALE;
+        for row in 0..GLYPH_H {
+            for col in 0..GLYPH_W {
+                if glyph(c) >> ((GLYPH_H - 1 - row) * GLYPH_W + (GLYPH_W - 1 - col)) & 1 == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    for dx in 0..GLYPH_SCALE {
+                        let px = glyph_x + col * GLYPH_SCALE + dx;
+                        let py = y + row * GLYPH_SCALE + dy;
+                        if px < max_x.min(img.width()) && py < img.height() {
+                            img.put_pixel(px, py, image::Rgb([255; 3]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 3x5 bitmap of the characters of the labels, rows from the top, unknown
+/// ones blank
+const fn glyph(c: char) -> u32 {
+    match c {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        '.' => 0b000_000_000_000_010,
+        '=' => 0b000_111_000_111_000,
+        'w' => 0b101_101_101_111_101,
+        'p' => 0b111_101_111_100_100,
+        'i' => 0b010_000_010_010_010,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values_and_ranges() {
+        let parsed = values("0.1:0.5:0.2,1", "weight").expect("valid values");
+        assert_eq!(parsed.len(), 4);
+        for (value, expected) in parsed.iter().zip([0.1, 0.3, 0.5, 1.0]) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+        assert!(values("0.5:0.1:0.1", "weight").is_err());
+        assert!(values("1:2", "weight").is_err());
+        assert!(values("nan", "weight").is_err());
+        assert!(values("0:1e9:1e-3", "weight").is_err());
+
+        assert_eq!(values("1,2,1,0:2:1", "iterations"), Ok(vec![1.0, 2.0, 0.0]));
+    }
+
+    #[test]
+    fn combines_settings() {
+        let combined = combinations(&[0.1, 0.3], &[0.001], &[10, 50]).expect("few combinations");
+        assert_eq!(combined.len(), 4);
+        assert_eq!(
+            combined[1],
+            Combination {
+                weight: 0.3,
+                pweight: 0.001,
+                iterations: 10
+            }
+        );
+
+        // Values converted to the same setting only appear once
+        assert_eq!(unique([1.2_f64, 1.4].map(|i| i.round() as usize)), [1]);
+
+        let weights = vec![0.1; 11];
+        let iterations = (0..100).collect::<Vec<_>>();
+        assert!(combinations(&weights, &[0.001], &iterations).is_err());
+    }
+
+    #[test]
+    fn parses_crops() {
+        assert_eq!(crop(None, 1000, 100), Ok((372, 0, 256, 100)));
+        assert_eq!(crop(Some("10, 20, 30, 40"), 40, 60), Ok((10, 20, 30, 40)));
+
+        assert!(crop(Some("10,20,31,40"), 40, 60).is_err());
+        assert!(crop(Some("0,0,0,10"), 40, 60).is_err());
+        assert!(crop(Some("4294967295,0,2,10"), 40, 60).is_err());
+        assert!(crop(Some("1,2,3"), 40, 60).is_err());
+    }
+}
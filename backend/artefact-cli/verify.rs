@@ -0,0 +1,66 @@
+use artefact_lib::{JpegSource, verify};
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    /// The processed image
+    #[arg(index = 1)]
+    image: String,
+
+    /// The source jpeg file
+    #[arg(index = 2)]
+    jpeg: String,
+
+    /// List every block with coefficients outside their quantization interval
+    #[arg(long, default_value = "false")]
+    blocks: bool,
+}
+
+pub fn run(args: VerifyArgs) {
+    let image = match artefact_lib::image::open(&args.image) {
+        Ok(image) => image.to_rgb8(),
+        Err(e) => {
+            eprintln!("Error: cannot open {}: {e}", args.image);
+            std::process::exit(2);
+        }
+    };
+
+    let report = match verify(&image, JpegSource::File(args.jpeg)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            std::process::exit(2);
+        }
+    };
+
+    for (c, component) in report.components.iter().enumerate() {
+        let checked_blocks = component.checked_blocks();
+        println!(
+            "component {c}: {}/{} coefficients outside their interval, in {}/{checked_blocks} blocks, max distance {:.3}",
+            component.violations(),
+            checked_blocks * 64,
+            component.violating_blocks(),
+            component.max_distance,
+        );
+
+        let skipped = component.blocks.len() - checked_blocks;
+        if skipped > 0 {
+            println!("  {skipped} blocks past the image edge not checked");
+        }
+
+        if args.blocks {
+            for (i, violations) in component.blocks.iter().enumerate() {
+                if let Some(violations @ 1..) = violations {
+                    println!(
+                        "  block ({}, {}): {violations}",
+                        i % component.block_w as usize,
+                        i / component.block_w as usize
+                    );
+                }
+            }
+        }
+    }
+
+    if !report.is_consistent() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use clap::ArgMatches;
+use notify::{RecursiveMode, Watcher};
+
+use crate::{
+    Args, EncoderSettings, OutputArgs, SolverArgs, artefact, batch,
+    config::{self, ConfigArgs},
+    process_file, schedule,
+};
+
+/// Name of the record of processed files in the output directory
+const RECORD_NAME: &str = ".artefact-watch";
+
+/// Time between two scans when file system events are not available
+const FALLBACK_POLL: Duration = Duration::from_secs(2);
+
+/// Time between two scans when waiting for file system events, in case some
+/// are missed
+const RESCAN: Duration = Duration::from_secs(60);
+
+#[derive(clap::Args, Debug)]
+pub struct WatchArgs {
+    /// The directories to watch for JPEG files
+    #[arg(index = 1, required = true, value_name = "DIR")]
+    dirs: Vec<String>,
+
+    /// Also watch the subdirectories
+    #[arg(short, long, default_value = "false")]
+    recursive: bool,
+
+    /// Write the outputs to this directory, mirroring the watched
+    /// directories. Outputs of changed files are overwritten
+    #[arg(long)]
+    out_dir: String,
+
+    /// Naming template of the outputs, `{stem}` is the input file name without
+    /// extension and `{ext}` the output extension, defaults to `{stem}.{ext}`
+    #[arg(long)]
+    name: Option<String>,
+
+    #[command(flatten)]
+    outputs: OutputArgs,
+
+    /// Number of images processed at once, defaults to the number of cores
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Memory budget of the images processed at once in MiB, estimated from
+    /// their dimensions, defaults to half of the available memory
+    #[arg(long)]
+    memory: Option<usize>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(flatten)]
+    solver: SolverArgs,
+
+    /// Move the originals to this directory once processed, mirroring the
+    /// watched directories
+    #[arg(long)]
+    archive_dir: Option<String>,
+
+    /// Scan the directories every this many seconds instead of waiting for
+    /// file system events, for network drives
+    #[arg(long)]
+    poll: Option<f64>,
+
+    /// Milliseconds a file must keep the same size and modification time
+    /// before being processed, so that files being written are left alone
+    #[arg(long, default_value = "1000")]
+    settle: u64,
+
+    /// Record of the processed files, kept across restarts, defaults to
+    /// `.artefact-watch` in the output directory
+    #[arg(long)]
+    record: Option<PathBuf>,
+}
+
+/// Size and modification time of a file, to tell when it changed
+type Stamp = (u64, u128);
+
+/// Processed files, appended to a file as they are processed
+struct Record {
+    path: PathBuf,
+    stamps: HashMap<PathBuf, Stamp>,
+}
+
+impl Record {
+    /// Read the record, one `size<TAB>mtime<TAB>path` line per processed file,
+    /// later lines taking precedence
+    fn load(path: PathBuf) -> Self {
+        let stamps = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let size = fields.next()?.parse().ok()?;
+                let mtime = fields.next()?.parse().ok()?;
+                Some((PathBuf::from(fields.next()?), (size, mtime)))
+            })
+            .collect();
+        Self { path, stamps }
+    }
+
+    fn contains(&self, file: &Path, stamp: Stamp) -> bool {
+        self.stamps.get(file) == Some(&stamp)
+    }
+
+    fn insert(&mut self, file: PathBuf, stamp: Stamp) -> Result<(), String> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut record| writeln!(record, "{}\t{}\t{}", stamp.0, stamp.1, file.display()))
+            .map_err(|e| format!("Cannot write {}: {e}", self.path.display()))?;
+        self.stamps.insert(file, stamp);
+        Ok(())
+    }
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), mtime.as_nanos()))
+}
+
+/// Process the JPEGs of the watched directories as they appear or change,
+/// until interrupted
+pub fn run(mut watch: WatchArgs, matches: &ArgMatches) {
+    if let Err(e) = config::apply(
+        &watch.config,
+        &mut watch.solver,
+        Some(&mut watch.outputs),
+        matches,
+    ) {
+        eprintln!("Error: {e}");
+        std::process::exit(2);
+    }
+    let out_dir = PathBuf::from(&watch.out_dir);
+
+    // The main command on the watched directories, each file on its own
+    let args = Args {
+        input: watch.dirs,
+        output: None,
+        out_dir: Some(watch.out_dir),
+        name: watch.name,
+        outputs: watch.outputs,
+        recursive: watch.recursive,
+        jobs: watch.jobs,
+        memory: watch.memory,
+        overwrite: true,
+        cache: false,
+        force: false,
+        config: watch.config,
+        solver: watch.solver,
+        temporal_weight: 0.0,
+        warm_start: false,
+        sequence: false,
+        image: None,
+        benchmark: false,
+        diff: Vec::new(),
+        diff_gain: 0.0,
+        repeat: 1,
+    };
+    if let Some(dir) = args.input.iter().find(|dir| !Path::new(dir).is_dir()) {
+        eprintln!("Error: {dir} is not a directory");
+        std::process::exit(2);
+    }
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        eprintln!("Error: cannot create {}: {e}", out_dir.display());
+        std::process::exit(2);
+    }
+
+    let artefact = artefact(&args);
+    let settings = EncoderSettings {
+        quality: args.outputs.quality,
+        effort: args.outputs.effort,
+    };
+    let jobs = args.jobs.unwrap_or_else(schedule::default_jobs);
+    let budget = schedule::Budget::with_memory(args.memory);
+    let settle = Duration::from_millis(watch.settle);
+    let mut record = Record::load(watch.record.unwrap_or_else(|| out_dir.join(RECORD_NAME)));
+
+    // Events only wake the loop up, the directories are scanned either way
+    let (sender, events) = mpsc::channel();
+    let watcher = match watch.poll {
+        Some(_) => None,
+        None => notify::recommended_watcher(move |_| {
+            let _ = sender.send(());
+        })
+        .and_then(|mut watcher| {
+            let mode = if args.recursive {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            for dir in &args.input {
+                watcher.watch(Path::new(dir), mode)?;
+            }
+            Ok(watcher)
+        })
+        .inspect_err(|e| eprintln!("Cannot watch for file system events ({e}), polling instead"))
+        .ok(),
+    };
+    let interval = match (watch.poll, &watcher) {
+        (Some(seconds), _) => Duration::from_secs_f64(seconds.max(0.1)),
+        (None, Some(_)) => RESCAN,
+        (None, None) => FALLBACK_POLL,
+    };
+
+    eprintln!(
+        "Watching {}, outputs to {}",
+        args.input.join(", "),
+        out_dir.display()
+    );
+
+    // Files seen changing, with the time since they have kept the same stamp
+    let mut pending = HashMap::<PathBuf, (Stamp, Instant)>::new();
+    loop {
+        let (inputs, errors) = batch::collect(&args.input, args.recursive);
+        for error in errors.iter().filter(|e| !e.ends_with("no matching file")) {
+            eprintln!("Error: {error}");
+        }
+
+        let now = Instant::now();
+        let mut ready = Vec::new();
+        for input in inputs {
+            let Some(stamp) = stamp(&input.path) else {
+                continue;
+            };
+            let key = input.path.canonicalize().unwrap_or(input.path.clone());
+            if record.contains(&key, stamp) {
+                pending.remove(&key);
+                continue;
+            }
+            match pending.get(&key) {
+                Some((pending_stamp, since)) if *pending_stamp == stamp => {
+                    if now.duration_since(*since) >= settle {
+                        pending.remove(&key);
+                        ready.push((input, key, stamp));
+                    }
+                }
+                _ => {
+                    pending.insert(key, (stamp, now));
+                }
+            }
+        }
+
+        let results = schedule::run(&ready, jobs, |_, (input, _, _)| {
+            eprintln!("Processing {}", input.path.display());
+            let result = process_file(&args, &artefact, settings, &budget, jobs, None, input)
+                .and_then(|()| match &watch.archive_dir {
+                    Some(archive_dir) => {
+                        archive(&input.path, &Path::new(archive_dir).join(&input.relative))
+                    }
+                    None => Ok(()),
+                });
+            if let Err(e) = &result {
+                eprintln!("Error in {}: {e}", input.path.display());
+            }
+            result
+        });
+
+        // Failed files are recorded too, so they are only retried once changed
+        for ((_, key, stamp), _) in ready.into_iter().zip(results) {
+            if let Err(e) = record.insert(key, stamp) {
+                eprintln!("Error: {e}");
+            }
+        }
+
+        let timeout = if pending.is_empty() {
+            interval
+        } else {
+            interval.min(settle)
+        };
+        match events.recv_timeout(timeout) {
+            Ok(()) => {
+                // Coalesce the bursts of events of a file being written
+                std::thread::sleep(Duration::from_millis(100));
+                while events.try_recv().is_ok() {}
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
+
+/// Move a processed original, copying it if it is on another file system
+fn archive(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+    }
+    std::fs::rename(from, to)
+        .or_else(|_| std::fs::copy(from, to).and_then(|_| std::fs::remove_file(from)))
+        .map_err(|e| format!("Cannot move to {}: {e}", to.display()))
+}
@@ -0,0 +1,216 @@
+//! Discovery of the JPEG images stored in a file
+//!
+//! Besides the primary image, camera JPEGs carry an EXIF thumbnail in their
+//! APP1 segment, and MPO (multi-picture) files append more images after the
+//! primary EOI: the other view of a stereo pair, depth maps, ...
+
+/// Where an image was found in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddedKind {
+    /// First image of the file, the one decoded by default
+    Primary,
+    /// Image stored after the primary one, e.g. in an MPO file
+    Appended,
+    /// Preview stored in an APP segment (EXIF, `FlashPix`) of the image before it
+    Thumbnail,
+}
+
+/// A JPEG image found by [`embedded_jpegs`]
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedJpeg<'a> {
+    pub kind: EmbeddedKind,
+    /// Offset of the SOI marker in the file
+    pub offset: usize,
+    /// The whole JPEG stream, from SOI to EOI
+    pub data: &'a [u8],
+}
+
+/// Every JPEG image stored in `data`: each top-level image followed by the
+/// thumbnails in its APP segments
+///
+/// Index 0 is the image that is processed when the file is passed as is.
+#[must_use]
+pub fn embedded_jpegs(data: &[u8]) -> Vec<EmbeddedJpeg<'_>> {
+    let offset_of = |image: &[u8]| image.as_ptr() as usize - data.as_ptr() as usize;
+
+    let mut jpegs = Vec::new();
+    for (i, image) in scan_jpegs(data).into_iter().enumerate() {
+        jpegs.push(EmbeddedJpeg {
+            kind: if i == 0 {
+                EmbeddedKind::Primary
+            } else {
+                EmbeddedKind::Appended
+            },
+            offset: offset_of(image),
+            data: image,
+        });
+
+        for thumbnail in app_segments(image).flat_map(scan_jpegs) {
+            jpegs.push(EmbeddedJpeg {
+                kind: EmbeddedKind::Thumbnail,
+                offset: offset_of(thumbnail),
+                data: thumbnail,
+            });
+        }
+    }
+    jpegs
+}
+
+/// Payloads of the `APPn` segments of the JPEG image at the start of `data`
+fn app_segments(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        loop {
+            if *data.get(pos)? != 0xFF {
+                return None;
+            }
+            while *data.get(pos)? == 0xFF {
+                pos += 1;
+            }
+            let marker = data[pos];
+            pos += 1;
+
+            match marker {
+                // Standalone markers
+                0x01 | 0xD0..=0xD7 => {}
+                // Image data starts, no more APP segments
+                0xD9 | 0xDA => return None,
+                _ => {
+                    let len =
+                        usize::from(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+                    let payload = data.get(pos + 2..pos + len.max(2))?;
+                    pos += len.max(2);
+
+                    if (0xE0..=0xEF).contains(&marker) {
+                        return Some(payload);
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Complete JPEG images found anywhere in `data`
+pub fn scan_jpegs(data: &[u8]) -> Vec<&[u8]> {
+    let mut images = Vec::new();
+    let mut pos = 0;
+
+    while let Some(start) = data[pos..]
+        .windows(3)
+        .position(|w| w == [0xFF, 0xD8, 0xFF])
+        .map(|offset| pos + offset)
+    {
+        if let Some(len) = jpeg_len(&data[start..]) {
+            images.push(&data[start..start + len]);
+            pos = start + len;
+        } else {
+            // Truncated or not a JPEG, keep looking after this SOI
+            pos = start + 2;
+        }
+    }
+
+    images
+}
+
+/// Length of the JPEG image at the start of `data`, up to and including its
+/// EOI marker, or `None` if it is truncated or malformed
+pub fn jpeg_len(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Skip fill bytes
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos];
+        pos += 1;
+
+        match marker {
+            // EOI
+            0xD9 => return Some(pos),
+            // Standalone markers
+            0x01 | 0xD0..=0xD7 => {}
+            _ => {
+                let len = usize::from(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+                if len < 2 {
+                    return None;
+                }
+                pos += len;
+
+                // SOS, skip the entropy coded data up to the next marker that
+                // is not a stuffed byte or a restart marker
+                if marker == 0xDA {
+                    loop {
+                        match (*data.get(pos)?, *data.get(pos + 1)?) {
+                            (0xFF, 0x00 | 0xD0..=0xD7) => pos += 2,
+                            (0xFF, 0xFF) => pos += 1,
+                            (0xFF, _) => break,
+                            _ => pos += 1,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixtures;
+
+    #[test]
+    fn measures_jpegs() {
+        let jpeg = fixtures::jpeg(24, 16);
+        assert_eq!(jpeg_len(&jpeg), Some(jpeg.len()));
+        assert_eq!(
+            jpeg_len(&[jpeg.as_slice(), b"trailing"].concat()),
+            Some(jpeg.len())
+        );
+
+        assert_eq!(jpeg_len(&jpeg[..jpeg.len() - 1]), None);
+        assert_eq!(jpeg_len(&jpeg[1..]), None);
+        // Segment length below its own size
+        assert_eq!(jpeg_len(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x01]), None);
+    }
+
+    #[test]
+    fn finds_thumbnails_and_appended_images() {
+        let thumbnail = fixtures::jpeg(8, 8);
+        let primary = fixtures::with_thumbnail(&fixtures::jpeg(32, 16), &thumbnail);
+        let appended = fixtures::jpeg(16, 16);
+        let data = [primary.as_slice(), b"padding", &appended].concat();
+
+        let jpegs = embedded_jpegs(&data);
+        let kinds = jpegs.iter().map(|jpeg| jpeg.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                EmbeddedKind::Primary,
+                EmbeddedKind::Thumbnail,
+                EmbeddedKind::Appended
+            ]
+        );
+
+        assert_eq!((jpegs[0].offset, jpegs[0].data), (0, primary.as_slice()));
+        // After SOI, the APP1 marker and length, and the EXIF header
+        assert_eq!((jpegs[1].offset, jpegs[1].data), (12, thumbnail.as_slice()));
+        assert_eq!(jpegs[2].offset, primary.len() + 7);
+        assert_eq!(jpegs[2].data, appended);
+    }
+
+    #[test]
+    fn skips_truncated_images() {
+        let jpeg = fixtures::jpeg(16, 16);
+        let data = [&jpeg[..jpeg.len() / 2], &jpeg].concat();
+
+        assert_eq!(scan_jpegs(&data), [jpeg.as_slice()]);
+        assert!(embedded_jpegs(b"no image here").is_empty());
+    }
+}
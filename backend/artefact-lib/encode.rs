@@ -0,0 +1,116 @@
+use std::io::Cursor;
+
+use image::{
+    ImageFormat, RgbImage,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder},
+};
+use zune_core::{bit_depth::BitDepth, colorspace::ColorSpace, options::EncoderOptions};
+use zune_jpegxl::JxlSimpleEncoder;
+
+/// Output image format, with the settings of the lossy encoders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Webp,
+    Tiff,
+    Bmp,
+    Jpeg {
+        /// 1-100
+        quality: u8,
+    },
+    Avif {
+        /// 1-100
+        quality: u8,
+        /// 1-10, higher is slower and gives smaller files
+        effort: u8,
+    },
+    /// Lossless JPEG XL
+    Jxl,
+}
+
+impl OutputFormat {
+    /// Default quality of the lossy formats
+    pub const DEFAULT_QUALITY: u8 = 90;
+
+    /// Default effort of the AVIF encoder
+    pub const DEFAULT_EFFORT: u8 = 7;
+
+    /// Format matching a file extension, with the given settings for the
+    /// lossy formats
+    #[must_use]
+    pub fn from_extension(extension: &str, quality: u8, effort: u8) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            "tif" | "tiff" => Some(Self::Tiff),
+            "bmp" => Some(Self::Bmp),
+            "jpg" | "jpeg" => Some(Self::Jpeg { quality }),
+            "avif" => Some(Self::Avif { quality, effort }),
+            "jxl" => Some(Self::Jxl),
+            _ => None,
+        }
+    }
+}
+
+/// Encode an image in memory
+///
+/// # Errors
+/// Returns an error if the encoder fails.
+pub fn encode(image: &RgbImage, format: OutputFormat) -> Result<Vec<u8>, String> {
+    let mut cursor = Cursor::new(Vec::new());
+
+    match format {
+        OutputFormat::Png => image.write_to(&mut cursor, ImageFormat::Png),
+        OutputFormat::Webp => image.write_to(&mut cursor, ImageFormat::WebP),
+        OutputFormat::Tiff => image.write_to(&mut cursor, ImageFormat::Tiff),
+        OutputFormat::Bmp => image.write_to(&mut cursor, ImageFormat::Bmp),
+        OutputFormat::Jpeg { quality } => image.write_with_encoder(JpegEncoder::new_with_quality(
+            &mut cursor,
+            quality.clamp(1, 100),
+        )),
+        OutputFormat::Avif { quality, effort } => {
+            image.write_with_encoder(AvifEncoder::new_with_speed_quality(
+                &mut cursor,
+                11 - effort.clamp(1, 10),
+                quality.clamp(1, 100),
+            ))
+        }
+        OutputFormat::Jxl => return encode_jxl(image),
+    }
+    .map_err(|e| format!("Can't encode image: {e}"))?;
+
+    Ok(cursor.into_inner())
+}
+
+/// Encode an image to a lossless JPEG XL, in the calling thread to also run
+/// in the browser
+fn encode_jxl(image: &RgbImage) -> Result<Vec<u8>, String> {
+    let options = EncoderOptions::new(
+        image.width() as usize,
+        image.height() as usize,
+        ColorSpace::RGB,
+        BitDepth::Eight,
+    );
+    let mut data = Vec::new();
+    JxlSimpleEncoder::new(image.as_raw(), options)
+        .encode(&mut data)
+        .map_err(|e| format!("Can't encode image: {e}"))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_lossless_jxl() {
+        let image = RgbImage::from_fn(300, 20, |x, y| image::Rgb([x as u8, y as u8, 128]));
+        let format = OutputFormat::from_extension("JXL", 50, 1);
+        assert_eq!(format, Some(OutputFormat::Jxl));
+
+        let data = encode(&image, OutputFormat::Jxl).expect("encodable image");
+        // Bare codestream signature
+        assert_eq!(data[..2], [0xFF, 0x0A]);
+        assert!(encode(&RgbImage::new(1, 1), OutputFormat::Jxl).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use zune_jpeg::{JpegDecoder, zune_core::bytestream::ZCursor};
+
+/// Quantization tables of the JPEG standard (Annex K) used by the IJG
+/// encoder, luma then chroma, natural order
+const STANDARD_QUANT_TABLES: [[u16; 64]; 2] = [
+    [
+        16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69,
+        56, 14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81,
+        104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+    ],
+    [
+        17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99,
+        99, 47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    ],
+];
+
+/// Header information of one component, see [`JpegInfo`]
+#[derive(Debug, Clone)]
+pub struct ComponentInfo {
+    /// Identifier in the frame header
+    pub id: u8,
+    /// Sampling factors in the frame header, the component with the largest
+    /// ones is at full resolution
+    pub horizontal_samp: u8,
+    pub vertical_samp: u8,
+    pub quant_table_number: u8,
+    /// Natural order
+    pub quant_table: [u16; 64],
+
+    /// Size of the component rounded up to whole MCUs, as processed
+    pub rounded_px_w: u32,
+    pub rounded_px_h: u32,
+    pub rounded_px_count: u32,
+    pub block_w: u32,
+    pub block_h: u32,
+    pub block_count: u32,
+}
+
+/// Header information of a JPEG, read without decoding the image
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct JpegInfo {
+    pub width: u32,
+    pub height: u32,
+    pub progressive: bool,
+    /// Number of MCUs between restart markers, 0 if there are none
+    pub restart_interval: u32,
+    pub icc_profile: bool,
+    pub exif: bool,
+    /// Quality of the IJG encoder whose quantization tables are the closest,
+    /// see [`JpegInfo::quality_exact`]
+    pub quality: u8,
+    /// Whether the quantization tables are exactly those of `quality`, rather
+    /// than from another encoder
+    pub quality_exact: bool,
+    pub components: Vec<ComponentInfo>,
+}
+
+/// Read the headers of a JPEG, up to its first scan
+///
+/// # Errors
+/// Returns an error if the headers are not valid.
+pub fn jpeg_info(data: &[u8]) -> Result<JpegInfo, String> {
+    let mut decoder = JpegDecoder::new(ZCursor::new(data));
+    decoder
+        .decode_headers()
+        .map_err(|e| format!("Failed to read JPEG headers: {e}"))?;
+    let (width, height) = decoder
+        .dimensions()
+        .ok_or("Failed to read JPEG headers: no dimensions")?;
+
+    let components = decoder
+        .components
+        .iter()
+        .map(|comp| {
+            let block_w = u32::from(comp.rounded_px_w / 8);
+            let block_h = u32::from(comp.rounded_px_h / 8);
+            ComponentInfo {
+                id: comp.id,
+                horizontal_samp: comp.horizontal_samp.u8(),
+                vertical_samp: comp.vertical_samp.u8(),
+                quant_table_number: comp.quant_table_number,
+                quant_table: comp.quant_table.map(|q| q.clamp(0, 0xFFFF) as u16),
+                rounded_px_w: comp.rounded_px_w.into(),
+                rounded_px_h: comp.rounded_px_h.into(),
+                rounded_px_count: comp.rounded_px_count as u32,
+                block_w,
+                block_h,
+                block_count: block_w * block_h,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (quality, quality_exact) = estimate_quality(&components);
+
+    Ok(JpegInfo {
+        width: width.into(),
+        height: height.into(),
+        progressive: decoder.is_progressive(),
+        restart_interval: decoder.restart_interval() as u32,
+        icc_profile: decoder.icc_profile().is_some(),
+        exif: decoder.exif().is_some(),
+        quality,
+        quality_exact,
+        components,
+    })
+}
+
+/// IJG quality whose scaled standard tables are the closest to the tables of
+/// the components, luma against the luma table and chroma against the chroma
+/// one, and whether they match exactly
+fn estimate_quality(components: &[ComponentInfo]) -> (u8, bool) {
+    let distance = |quality: u8| {
+        components
+            .iter()
+            .enumerate()
+            .map(|(c, comp)| {
+                let standard = &STANDARD_QUANT_TABLES[usize::from(c > 0)];
+                comp.quant_table
+                    .iter()
+                    .zip(scale_quant_table(standard, quality))
+                    .map(|(&q, scaled)| u32::from(q.abs_diff(scaled)))
+                    .sum::<u32>()
+            })
+            .sum::<u32>()
+    };
+
+    let quality = (1..=100).min_by_key(|&q| distance(q)).unwrap_or(100);
+    (quality, distance(quality) == 0)
+}
+
+/// Standard table scaled to a quality like the IJG encoder, with baseline
+/// (8 bit) steps
+fn scale_quant_table(standard: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = u32::from(quality.clamp(1, 100));
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    standard.map(|q| ((u32::from(q) * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixtures;
+
+    #[test]
+    fn reads_headers() {
+        let info = jpeg_info(&fixtures::jpeg(40, 20)).expect("valid JPEG");
+        assert_eq!((info.width, info.height), (40, 20));
+        assert!(!info.progressive);
+        assert_eq!(info.components.len(), 3);
+
+        // 4:4:4, rounded up to whole blocks
+        for component in &info.components {
+            assert_eq!((component.horizontal_samp, component.vertical_samp), (1, 1));
+            assert_eq!((component.block_w, component.block_h), (5, 3));
+            assert_eq!(component.block_count, 15);
+        }
+        assert_eq!(info.components[1].quant_table_number, 1);
+
+        assert!(jpeg_info(b"not a JPEG").is_err());
+    }
+
+    #[test]
+    fn estimates_memory_from_headers() {
+        let jpeg = fixtures::jpeg(40, 20);
+        let info = jpeg_info(&jpeg).expect("valid JPEG");
+        let decoded = crate::Jpeg::from(crate::JpegSource::Buffer(jpeg)).expect("valid JPEG");
+
+        for artefact in [
+            crate::Artefact::default(),
+            crate::Artefact::default().fast(true),
+        ] {
+            assert_eq!(
+                artefact.memory_estimate_from_headers(&info),
+                artefact.memory_estimate(&decoded)
+            );
+        }
+    }
+
+    #[test]
+    fn estimates_the_quality() {
+        let info = jpeg_info(&fixtures::jpeg(16, 16)).expect("valid JPEG");
+        assert_eq!((info.quality, info.quality_exact), (75, true));
+    }
+
+    #[test]
+    fn estimates_the_closest_quality() {
+        let component = |c: usize, quant_table| ComponentInfo {
+            id: c as u8 + 1,
+            horizontal_samp: 1,
+            vertical_samp: 1,
+            quant_table_number: u8::from(c > 0),
+            quant_table,
+            rounded_px_w: 8,
+            rounded_px_h: 8,
+            rounded_px_count: 64,
+            block_w: 1,
+            block_h: 1,
+            block_count: 1,
+        };
+
+        // Quality 50 is the standard tables as is
+        assert_eq!(
+            scale_quant_table(&STANDARD_QUANT_TABLES[0], 50),
+            STANDARD_QUANT_TABLES[0]
+        );
+        let standard = [0, 1, 2].map(|c| component(c, STANDARD_QUANT_TABLES[usize::from(c > 0)]));
+        assert_eq!(estimate_quality(&standard), (50, true));
+
+        let mut tweaked = [0, 1, 2].map(|c| {
+            component(
+                c,
+                scale_quant_table(&STANDARD_QUANT_TABLES[usize::from(c > 0)], 90),
+            )
+        });
+        tweaked[0].quant_table[0] += 1;
+        assert_eq!(estimate_quality(&tweaked), (90, false));
+    }
+}
@@ -1,10 +1,12 @@
 #[cfg(feature = "moz")]
 mod moz;
+mod npz;
 #[cfg(not(feature = "moz"))]
 mod zune;
 
 use zune_jpeg::sample_factor::SampleFactor;
 
+/// Quantized DCT coefficients of one component
 #[derive(Debug, Clone)]
 pub struct Coefficient {
     /// Rounded up until the next multiple of 8
@@ -19,14 +21,19 @@ pub struct Coefficient {
     pub block_h: u32,
     pub block_count: u32,
 
+    /// Horizontal downsampling of the component relative to the full image
     pub horizontal_samp_factor: SampleFactor,
+    /// Vertical downsampling of the component relative to the full image
     pub vertical_samp_factor: SampleFactor,
 
+    /// 64 coefficients per block, blocks in raster order, coefficients in natural order
     pub dct_coefs: Vec<f32>,
+    /// Natural order
     pub quant_table: [f32; 64],
 }
 
-#[derive(Debug)]
+/// Decoded JPEG coefficients, before any processing
+#[derive(Debug, Clone)]
 pub struct Jpeg {
     pub nchannel: u32,
     pub real_px_w: u32,
@@ -39,4 +46,215 @@ pub struct Jpeg {
 pub enum JpegSource {
     File(String),
     Buffer(Vec<u8>),
+    /// Coefficients previously exported with [`Jpeg::to_npz`]
+    Npz(Vec<u8>),
+    /// Coefficients from another decoder or generated, see [`Jpeg::new`].
+    /// Checked again when read, in case the fields were modified directly
+    Coefficients(Jpeg),
+}
+
+impl Coefficient {
+    /// Create a component from its quantized blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `block_w` - Number of 8x8 blocks per row
+    /// * `block_h` - Number of 8x8 blocks per column
+    /// * `horizontal_samp_factor` - Horizontal downsampling relative to the full image
+    /// * `vertical_samp_factor` - Vertical downsampling relative to the full image
+    /// * `dct_coefs` - 64 quantized coefficients per block, blocks in raster
+    ///   order, coefficients in natural (not zigzag) order
+    /// * `quant_table` - Quantization table in natural order
+    ///
+    /// # Errors
+    /// Returns an error if the number of coefficients does not match the
+    /// number of blocks, if the component is too large, or if a quantization
+    /// step is not strictly positive.
+    pub fn new(
+        block_w: u32,
+        block_h: u32,
+        horizontal_samp_factor: SampleFactor,
+        vertical_samp_factor: SampleFactor,
+        dct_coefs: Vec<f32>,
+        quant_table: [f32; 64],
+    ) -> Result<Self, String> {
+        let too_large = || format!("Component of {block_w}x{block_h} blocks is too large");
+        let block_count = block_w.checked_mul(block_h).ok_or_else(too_large)?;
+        let rounded_px_w = block_w.checked_mul(8).ok_or_else(too_large)?;
+        let rounded_px_h = block_h.checked_mul(8).ok_or_else(too_large)?;
+        let rounded_px_count = block_count.checked_mul(64).ok_or_else(too_large)?;
+
+        if block_count == 0 {
+            return Err("A component needs at least one block".to_string());
+        }
+        if dct_coefs.len() != rounded_px_count as usize {
+            return Err(format!(
+                "Expected {rounded_px_count} coefficients for {block_w}x{block_h} blocks, got {}",
+                dct_coefs.len()
+            ));
+        }
+        if quant_table.iter().any(|&q| q.is_nan() || q <= 0.0) {
+            return Err("Quantization steps must be strictly positive".to_string());
+        }
+
+        Ok(Self {
+            rounded_px_w,
+            rounded_px_h,
+            rounded_px_count,
+            block_w,
+            block_h,
+            block_count,
+            horizontal_samp_factor,
+            vertical_samp_factor,
+            dct_coefs,
+            quant_table,
+        })
+    }
+}
+
+impl Jpeg {
+    /// Assemble an image from its components, Y or Y, Cb, Cr
+    ///
+    /// # Errors
+    /// Returns an error if there are not 1 or 3 components, or if the
+    /// components, once upsampled, do not all cover the same area of at least
+    /// `real_px_w` x `real_px_h` pixels.
+    pub fn new(real_px_w: u32, real_px_h: u32, coefs: Vec<Coefficient>) -> Result<Self, String> {
+        if !matches!(coefs.len(), 1 | 3) {
+            return Err(format!("Expected 1 or 3 components, got {}", coefs.len()));
+        }
+
+        let covered = |coef: &Coefficient| {
+            coef.rounded_px_w
+                .checked_mul(coef.horizontal_samp_factor.u32())
+                .zip(
+                    coef.rounded_px_h
+                        .checked_mul(coef.vertical_samp_factor.u32()),
+                )
+        };
+        let Some((w, h)) = covered(&coefs[0]) else {
+            return Err("Components are too large".to_string());
+        };
+        if w < real_px_w || h < real_px_h || coefs.iter().any(|coef| covered(coef) != Some((w, h)))
+        {
+            return Err("Components do not cover the image consistently".to_string());
+        }
+
+        Ok(Self {
+            nchannel: coefs.len() as u32,
+            real_px_w,
+            real_px_h,
+            coefs,
+        })
+    }
+
+    /// Check a `Jpeg` whose fields may have been set directly like [`Self::new`]
+    /// does, recomputing the sizes derived from the block counts
+    pub(crate) fn validated(self) -> Result<Self, String> {
+        let coefs = self
+            .coefs
+            .into_iter()
+            .enumerate()
+            .map(|(c, coef)| {
+                Coefficient::new(
+                    coef.block_w,
+                    coef.block_h,
+                    coef.horizontal_samp_factor,
+                    coef.vertical_samp_factor,
+                    coef.dct_coefs,
+                    coef.quant_table,
+                )
+                .map_err(|e| format!("Component {c}: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(self.real_px_w, self.real_px_h, coefs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Coefficient, Jpeg, JpegSource};
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    fn component(block_w: u32, block_h: u32, samp: SampleFactor) -> Result<Coefficient, String> {
+        Coefficient::new(
+            block_w,
+            block_h,
+            samp,
+            samp,
+            vec![0.0; (block_w * block_h) as usize * 64],
+            [1.0; 64],
+        )
+    }
+
+    #[test]
+    fn assembles_subsampled_components() {
+        let jpeg = Jpeg::new(
+            30,
+            14,
+            vec![
+                component(4, 2, SampleFactor::One).expect("valid component"),
+                component(2, 1, SampleFactor::Two).expect("valid component"),
+                component(2, 1, SampleFactor::Two).expect("valid component"),
+            ],
+        )
+        .expect("consistent components");
+        assert_eq!(jpeg.nchannel, 3);
+        assert_eq!(jpeg.coefs[1].rounded_px_count, 128);
+    }
+
+    #[test]
+    fn rejects_invalid_components() {
+        let one = SampleFactor::One;
+        assert!(component(0, 1, one).is_err());
+        assert!(Coefficient::new(1, 1, one, one, vec![0.0; 63], [1.0; 64]).is_err());
+        assert!(Coefficient::new(1, 1, one, one, vec![0.0; 64], [0.0; 64]).is_err());
+        assert!(Coefficient::new(u32::MAX, 2, one, one, Vec::new(), [1.0; 64]).is_err());
+        assert!(Coefficient::new(1 << 30, 1, one, one, Vec::new(), [1.0; 64]).is_err());
+
+        let y = || component(4, 2, one).expect("valid component");
+        assert!(
+            Jpeg::new(33, 16, vec![y()]).is_err(),
+            "larger than the blocks"
+        );
+        assert!(Jpeg::new(32, 16, vec![y(), y()]).is_err(), "2 components");
+        assert!(
+            Jpeg::new(
+                32,
+                16,
+                vec![y(), component(1, 1, SampleFactor::Two).expect("valid"), y()]
+            )
+            .is_err(),
+            "chroma covering less than luma"
+        );
+    }
+
+    #[test]
+    fn validates_modified_coefficients() {
+        let jpeg = || {
+            Jpeg::new(
+                32,
+                16,
+                vec![component(4, 2, SampleFactor::One).expect("valid component")],
+            )
+            .expect("consistent components")
+        };
+
+        let mut resized = jpeg();
+        resized.coefs[0].rounded_px_w = 64;
+        let resized = Jpeg::from(JpegSource::Coefficients(resized)).expect("consistent blocks");
+        assert_eq!(resized.coefs[0].rounded_px_w, 32);
+
+        let mut truncated = jpeg();
+        truncated.coefs[0].dct_coefs.truncate(64);
+        assert!(Jpeg::from(JpegSource::Coefficients(truncated)).is_err());
+
+        let mut widened = jpeg();
+        widened.real_px_w = 40;
+        assert!(Jpeg::from(JpegSource::Coefficients(widened)).is_err());
+
+        let mut shrunk = jpeg();
+        shrunk.coefs[0].block_h = 1;
+        assert!(Jpeg::from(JpegSource::Coefficients(shrunk)).is_err());
+    }
 }
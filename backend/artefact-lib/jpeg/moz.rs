@@ -55,7 +55,17 @@ pub enum MozDecoderErr {
 
 #[cfg(feature = "moz")]
 impl Jpeg {
+    /// Read the quantized coefficients of a JPEG, without decoding it to pixels
+    ///
+    /// # Errors
+    /// Returns an error if the source cannot be read or is not a valid JPEG.
     pub fn from(jpeg_source: JpegSource) -> Result<Jpeg, String> {
+        match jpeg_source {
+            JpegSource::Npz(npz) => return Jpeg::from_npz(&npz),
+            JpegSource::Coefficients(jpeg) => return jpeg.validated(),
+            _ => {}
+        }
+
         let mut decoder = MozDecoder::new().map_err(|e| e.to_string())?;
         decoder.set_source(jpeg_source).map_err(|e| e.to_string())?;
         decoder.read_header().map_err(|e| e.to_string())?;
@@ -128,6 +138,9 @@ impl MozDecoder {
                     buffer.len() as core::ffi::c_ulong,
                 );
             },
+            JpegSource::Npz(_) | JpegSource::Coefficients(_) => {
+                return Err(MozDecoderErr::Other("Not a JPEG bitstream".to_string()));
+            }
         }
         self.is_source_set = true;
         Ok(())
@@ -0,0 +1,298 @@
+//! Decoded coefficients as a numpy `.npz` archive
+//!
+//! | array       | dtype    | shape                      | content                                                      |
+//! | ----------- | -------- | -------------------------- | ------------------------------------------------------------ |
+//! | `size`      | `uint32` | `(2,)`                     | width and height of the image in pixels                      |
+//! | `sampling`  | `uint8`  | `(n, 2)`                   | horizontal and vertical downsampling of each component, 1 or 2 |
+//! | `quant_{c}` | `uint16` | `(8, 8)`                   | quantization table of component `c`                          |
+//! | `coefs_{c}` | `int16`  | `(block_h, block_w, 8, 8)` | quantized DCT coefficients of component `c`                  |
+//!
+//! Blocks are in raster order and coefficients in natural (not zigzag) order.
+//! Components are Y, Cb, Cr, or a single Y for grayscale images. Tables and
+//! coefficients that do not fit their integer dtype, e.g. ones loaded from
+//! modified floats, are written as `float32` instead.
+//!
+//! When loading, `quant_{c}` and `coefs_{c}` may use any integer or float
+//! dtype, so that modified coefficients can be fed back in.
+//!
+//! ```python
+//! import numpy as np
+//! npz = np.load("image.npz")
+//! luma_dc = npz["coefs_0"][:, :, 0, 0] * npz["quant_0"][0, 0]
+//! ```
+
+use std::io::{Cursor, Read, Write};
+
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+use zune_jpeg::sample_factor::SampleFactor;
+
+use crate::{
+    jpeg::{Coefficient, Jpeg},
+    utils::npy::{NpyArray, from_npy, to_npy},
+};
+
+impl Jpeg {
+    /// Serialize the coefficients to a `.npz` archive, see the module docs for the layout
+    ///
+    /// # Errors
+    /// Returns an error if writing the archive fails.
+    pub fn to_npz(&self) -> Result<Vec<u8>, String> {
+        let mut arrays = vec![
+            (
+                "size".to_string(),
+                to_npy(&[2], &[self.real_px_w, self.real_px_h]),
+            ),
+            (
+                "sampling".to_string(),
+                to_npy(
+                    &[self.coefs.len(), 2],
+                    &self
+                        .coefs
+                        .iter()
+                        .flat_map(|c| [c.horizontal_samp_factor.u8(), c.vertical_samp_factor.u8()])
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+        ];
+
+        for (c, coef) in self.coefs.iter().enumerate() {
+            arrays.push((
+                format!("quant_{c}"),
+                integers::<u16>(&coef.quant_table).map_or_else(
+                    || to_npy(&[8, 8], &coef.quant_table),
+                    |quant| to_npy(&[8, 8], &quant),
+                ),
+            ));
+
+            let shape = [coef.block_h as usize, coef.block_w as usize, 8, 8];
+            arrays.push((
+                format!("coefs_{c}"),
+                integers::<i16>(&coef.dct_coefs).map_or_else(
+                    || to_npy(&shape, &coef.dct_coefs),
+                    |dct_coefs| to_npy(&shape, &dct_coefs),
+                ),
+            ));
+        }
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, npy) in arrays {
+            zip.start_file(format!("{name}.npy"), options)
+                .map_err(|e| format!("Failed to write {name}.npy: {e}"))?;
+            zip.write_all(&npy)
+                .map_err(|e| format!("Failed to write {name}.npy: {e}"))?;
+        }
+
+        Ok(zip
+            .finish()
+            .map_err(|e| format!("Failed to write .npz archive: {e}"))?
+            .into_inner())
+    }
+
+    /// Deserialize the coefficients from a `.npz` archive, see the module docs for the layout
+    ///
+    /// # Errors
+    /// Returns an error if an array is missing or has an unexpected shape, or if
+    /// the components do not cover the same area.
+    pub fn from_npz(bytes: &[u8]) -> Result<Self, String> {
+        let mut zip = ZipArchive::new(Cursor::new(bytes))
+            .map_err(|e| format!("Failed to read .npz archive: {e}"))?;
+        let mut array = |name: &str| -> Result<NpyArray, String> {
+            let mut npy = Vec::new();
+            zip.by_name(&format!("{name}.npy"))
+                .map_err(|e| format!("Missing {name}.npy: {e}"))?
+                .read_to_end(&mut npy)
+                .map_err(|e| format!("Failed to read {name}.npy: {e}"))?;
+            from_npy(&npy).map_err(|e| format!("{name}.npy: {e}"))
+        };
+
+        let size = array("size")?;
+        let [real_px_w, real_px_h] = size.data[..] else {
+            return Err("size.npy must hold 2 values".to_string());
+        };
+        let dimension = |value: f64| {
+            integer::<u32>(value).ok_or_else(|| format!("Invalid image dimension {value}"))
+        };
+        let (real_px_w, real_px_h) = (dimension(real_px_w)?, dimension(real_px_h)?);
+
+        let sampling = array("sampling")?;
+        let &[nchannel, 2] = &sampling.shape[..] else {
+            return Err("sampling.npy must be of shape (n, 2)".to_string());
+        };
+
+        let mut coefs = Vec::with_capacity(nchannel);
+        for c in 0..nchannel {
+            let samp_factor = |value: f64| match integer::<u8>(value) {
+                Some(1) => Ok(SampleFactor::One),
+                Some(2) => Ok(SampleFactor::Two),
+                _ => Err(format!("Invalid sampling factor {value} for component {c}")),
+            };
+
+            let quant = array(&format!("quant_{c}"))?;
+            if quant.shape != [8, 8] {
+                return Err(format!("quant_{c}.npy must be of shape (8, 8)"));
+            }
+
+            let dct = array(&format!("coefs_{c}"))?;
+            let &[block_h, block_w, 8, 8] = &dct.shape[..] else {
+                return Err(format!(
+                    "coefs_{c}.npy must be of shape (block_h, block_w, 8, 8)"
+                ));
+            };
+
+            let blocks = |count: usize| {
+                u32::try_from(count).map_err(|_| format!("coefs_{c}.npy is too large"))
+            };
+            coefs.push(
+                Coefficient::new(
+                    blocks(block_w)?,
+                    blocks(block_h)?,
+                    samp_factor(sampling.data[c * 2])?,
+                    samp_factor(sampling.data[c * 2 + 1])?,
+                    dct.data.iter().map(|&x| x as f32).collect(),
+                    std::array::from_fn(|i| quant.data[i] as f32),
+                )
+                .map_err(|e| format!("Component {c}: {e}"))?,
+            );
+        }
+
+        Self::new(real_px_w, real_px_h, coefs)
+    }
+}
+
+/// `value` as a `T`, if it is an integer in its range
+fn integer<T: TryFrom<i64>>(value: f64) -> Option<T> {
+    if value.fract() == 0.0 {
+        T::try_from(value as i64).ok()
+    } else {
+        None
+    }
+}
+
+/// All of `values` as `T`, if they are integers in its range
+fn integers<T: TryFrom<i64>>(values: &[f32]) -> Option<Vec<T>> {
+    values.iter().map(|&x| integer(f64::from(x))).collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)] // integers, exact in f32
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use crate::{
+        jpeg::{Jpeg, JpegSource},
+        utils::{fixtures, npy::to_npy},
+    };
+
+    #[test]
+    fn round_trip() {
+        let jpeg = Jpeg::from(JpegSource::Buffer(fixtures::jpeg(19, 13))).expect("valid JPEG");
+        let loaded = Jpeg::from_npz(&jpeg.to_npz().expect("npz")).expect("valid npz");
+
+        assert_eq!(
+            (loaded.real_px_w, loaded.real_px_h, loaded.nchannel),
+            (jpeg.real_px_w, jpeg.real_px_h, jpeg.nchannel)
+        );
+        for (a, b) in loaded.coefs.iter().zip(&jpeg.coefs) {
+            assert_eq!((a.block_w, a.block_h), (b.block_w, b.block_h));
+            assert_eq!(a.horizontal_samp_factor, b.horizontal_samp_factor);
+            assert_eq!(a.vertical_samp_factor, b.vertical_samp_factor);
+            assert_eq!(a.quant_table, b.quant_table);
+            assert_eq!(a.dct_coefs, b.dct_coefs);
+        }
+    }
+
+    /// An archive of the given arrays, as written by `numpy.savez`
+    fn npz(arrays: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, npy) in arrays {
+            zip.start_file(format!("{name}.npy"), SimpleFileOptions::default())
+                .expect("zip entry");
+            zip.write_all(npy).expect("zip entry");
+        }
+        zip.finish().expect("zip").into_inner()
+    }
+
+    #[test]
+    fn imports_float_arrays() {
+        let coefs = (0..2 * 64)
+            .map(|i| (i % 7) as f32 - 3.0)
+            .collect::<Vec<_>>();
+        let jpeg = Jpeg::from_npz(&npz(&[
+            ("size", to_npy(&[2], &[16_u32, 8])),
+            ("sampling", to_npy(&[1, 2], &[1_u8, 1])),
+            ("quant_0", to_npy(&[8, 8], &[2.0_f32; 64])),
+            ("coefs_0", to_npy(&[1, 2, 8, 8], &coefs)),
+        ]))
+        .expect("valid npz");
+
+        assert_eq!((jpeg.real_px_w, jpeg.real_px_h, jpeg.nchannel), (16, 8, 1));
+        assert_eq!((jpeg.coefs[0].block_w, jpeg.coefs[0].block_h), (2, 1));
+        assert_eq!(jpeg.coefs[0].quant_table, [2.0; 64]);
+        assert_eq!(jpeg.coefs[0].dct_coefs, coefs);
+    }
+
+    #[test]
+    fn rejects_inconsistent_arrays() {
+        let size = || ("size", to_npy(&[2], &[16_u32, 8]));
+        let sampling = || ("sampling", to_npy(&[1, 2], &[1_u8, 1]));
+        let quant = || ("quant_0", to_npy(&[8, 8], &[2_u16; 64]));
+
+        // missing coefficients
+        assert!(Jpeg::from_npz(&npz(&[size(), sampling(), quant()])).is_err());
+        // not enough blocks for the size
+        assert!(
+            Jpeg::from_npz(&npz(&[
+                size(),
+                sampling(),
+                quant(),
+                ("coefs_0", to_npy(&[1, 1, 8, 8], &[0_i16; 64])),
+            ]))
+            .is_err()
+        );
+        // invalid sampling factor
+        assert!(
+            Jpeg::from_npz(&npz(&[
+                size(),
+                ("sampling", to_npy(&[1, 2], &[3_u8, 1])),
+                quant(),
+                ("coefs_0", to_npy(&[1, 2, 8, 8], &[0_i16; 128])),
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn keeps_non_integral_values() {
+        let mut jpeg = Jpeg::from(JpegSource::Buffer(fixtures::jpeg(16, 8))).expect("valid JPEG");
+        jpeg.coefs[0].dct_coefs[1] = 0.25;
+        jpeg.coefs[1].dct_coefs[0] = 40_000.0;
+        jpeg.coefs[2].quant_table[0] = 1.5;
+
+        let loaded = Jpeg::from_npz(&jpeg.to_npz().expect("npz")).expect("valid npz");
+        for (a, b) in loaded.coefs.iter().zip(&jpeg.coefs) {
+            assert_eq!(a.quant_table, b.quant_table);
+            assert_eq!(a.dct_coefs, b.dct_coefs);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let sampling = || ("sampling", to_npy(&[1, 2], &[1_u8, 1]));
+        let quant = || ("quant_0", to_npy(&[8, 8], &[2_u16; 64]));
+        let coefs = || ("coefs_0", to_npy(&[1, 2, 8, 8], &[0_i16; 128]));
+
+        for size in [[16.0_f32, -8.0], [16.0, 8.5], [16.0, 5e9]] {
+            let size = ("size", to_npy(&[2], &size));
+            assert!(Jpeg::from_npz(&npz(&[size, sampling(), quant(), coefs()])).is_err());
+        }
+
+        let size = || ("size", to_npy(&[2], &[16_u32, 8]));
+        for samp in [257.0_f32, 1.5, -1.0] {
+            let sampling = ("sampling", to_npy(&[1, 2], &[samp, 1.0]));
+            assert!(Jpeg::from_npz(&npz(&[size(), sampling, quant(), coefs()])).is_err());
+        }
+    }
+}
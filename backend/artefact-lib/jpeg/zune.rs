@@ -2,11 +2,20 @@ use crate::jpeg::{Coefficient, Jpeg, JpegSource};
 use zune_jpeg::{JpegDecoder, zune_core::bytestream::ZCursor};
 
 impl Jpeg {
+    /// Read the quantized coefficients of a JPEG, without decoding it to pixels
+    ///
+    /// # Errors
+    /// Returns an error if the source cannot be read or is not a valid JPEG.
+    ///
+    /// # Panics
+    /// If the decoder does not report the image dimensions after decoding.
     pub fn from(jpeg_source: JpegSource) -> Result<Self, String> {
         let buffer = match jpeg_source {
             JpegSource::File(path) => std::fs::read(&path)
                 .map_err(|e| format!("Failed to read JPEG file '{path}': {e}"))?,
             JpegSource::Buffer(buffer) => buffer,
+            JpegSource::Npz(npz) => return Self::from_npz(&npz),
+            JpegSource::Coefficients(jpeg) => return jpeg.validated(),
         };
 
         let mut img = JpegDecoder::new(ZCursor::new(&buffer));
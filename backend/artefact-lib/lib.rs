@@ -11,25 +11,78 @@
     clippy::branches_sharing_code
 )]
 
+mod embedded;
+mod encode;
+mod info;
 mod jpeg;
+mod metrics;
+mod mjpeg;
+mod pipeline_reference;
 mod pipeline_scalar;
 mod pipeline_simd_8;
 mod pipeline_simd_adaptive;
+mod planes;
 mod utils;
+mod verify;
+mod visualize;
 
 pub use image;
 use rayon::prelude::*;
+pub use zune_jpeg::sample_factor::SampleFactor;
 
-use jpeg::Jpeg;
-pub use jpeg::JpegSource;
-use utils::macros::mul_add;
+pub use embedded::{EmbeddedJpeg, EmbeddedKind, embedded_jpegs};
+pub use encode::{OutputFormat, encode};
+pub use info::{ComponentInfo, JpegInfo, jpeg_info};
+pub use jpeg::{Coefficient, Jpeg, JpegSource};
+pub use metrics::{psnr, ssim};
+pub use mjpeg::mjpeg_frames;
+pub use pipeline_reference::{TOLERANCE_MAX, TOLERANCE_RMS};
+pub use planes::{Plane, PlaneResolution, planes_to_npy};
+pub use utils::timing::{Stage, Timings, thread_count};
+use utils::{macros::mul_add, temporal::Temporal, timing::timed};
+pub use verify::{ComponentReport, VerifyReport, verify};
+pub use visualize::{bound_proximity_map, difference_map, heat_map, plain_decode};
 
 #[cfg(not(feature = "simd"))]
-use pipeline_scalar::compute;
+use pipeline_scalar::{Buffers, compute, deblock};
 #[cfg(all(feature = "simd", not(feature = "simd_adaptive")))]
-use pipeline_simd_8::compute;
+use pipeline_simd_8::{Buffers, compute, deblock};
 #[cfg(all(feature = "simd", feature = "simd_adaptive"))]
-use pipeline_simd_adaptive::compute;
+use pipeline_simd_adaptive::{Buffers, compute, deblock};
+
+/// Name of the `f32` pipeline enabled at compile time
+#[cfg(not(feature = "simd"))]
+const PIPELINE: &str = "scalar";
+#[cfg(all(feature = "simd", not(feature = "simd_adaptive")))]
+const PIPELINE: &str = "simd_8";
+#[cfg(all(feature = "simd", feature = "simd_adaptive"))]
+const PIPELINE: &str = "simd_adaptive";
+
+/// Name of the pipeline of [`Precision::Half`]
+#[cfg(not(feature = "simd"))]
+const PIPELINE_HALF: &str = "scalar_f16";
+#[cfg(all(feature = "simd", not(feature = "simd_adaptive")))]
+const PIPELINE_HALF: &str = "simd_8_f16";
+#[cfg(all(feature = "simd", feature = "simd_adaptive"))]
+const PIPELINE_HALF: &str = "simd_adaptive_f16";
+
+/// Float precision of the solver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// `f32`, using the fastest pipeline enabled at compile time
+    #[default]
+    Single,
+    /// `f64` reference pipeline, slow, meant as an oracle for the `f32` ones,
+    /// see [`TOLERANCE_RMS`] and [`TOLERANCE_MAX`]
+    Double,
+    /// `f32` arithmetic on the same pipeline as [`Self::Single`], with the
+    /// buffers that are only read back by the kernels stored as `f16`
+    ///
+    /// Takes 16 instead of 24 bytes per pixel and component for the working
+    /// buffers, about a quarter less peak memory overall, at the cost of
+    /// ~10-25% more time and a few levels of difference in a handful of pixels.
+    Half,
+}
 
 #[derive(Debug)]
 pub enum ValueCollection<T> {
@@ -47,12 +100,19 @@ impl<T: Copy> ValueCollection<T> {
 }
 
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Artefact {
     weight: ValueCollection<f32>,
     pweight: ValueCollection<f32>,
     iterations: ValueCollection<usize>,
+    chroma_guide: f32,
     separate_components: bool,
+    fast: bool,
+    pyramid: usize,
+    precision: Precision,
     benchmark: bool,
+    temporal_weight: f32,
+    warm_start: bool,
 
     source: Option<JpegSource>,
 }
@@ -63,8 +123,14 @@ impl Default for Artefact {
             weight: ValueCollection::ForAll(0.3),
             pweight: ValueCollection::ForAll(0.001),
             iterations: ValueCollection::ForAll(50),
+            chroma_guide: 0.0,
             separate_components: false,
+            fast: false,
+            pyramid: 0,
+            precision: Precision::Single,
             benchmark: false,
+            temporal_weight: 0.0,
+            warm_start: false,
             source: None,
         }
     }
@@ -92,122 +158,628 @@ impl Artefact {
         weight: ValueCollection<f32>,
         pweight: ValueCollection<f32>,
         iterations: ValueCollection<usize>,
+        chroma_guide: f32,
         benchmark: bool,
-        separate_components: bool
+        separate_components: bool,
+        fast: bool,
+        pyramid: usize,
+        precision: Precision,
+        temporal_weight: f32,
+        warm_start: bool
     );
 
+    /// Name of the pipeline the solver runs on with the current precision
+    #[must_use]
+    pub const fn pipeline(&self) -> &'static str {
+        match self.precision {
+            Precision::Single => PIPELINE,
+            Precision::Double => "reference_f64",
+            Precision::Half => PIPELINE_HALF,
+        }
+    }
+
     /// Process the JPEG and return an RGB image buffer.
     /// If `benchmark` is set, returns an error with the message "BENCHMARK".
     /// Otherwise, returns the processed image or an error message.
     /// # Errors
     /// Returns an error if the source is not set or if reading the JPEG fails.
     /// Also returns an error with the message "BENCHMARK" if benchmarking is enabled.
-    pub fn process(self) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, String> {
-        let jpeg = Jpeg::from(self.source.ok_or("Source is not set")?)
-            .map_err(|e| format!("Failed to read JPEG: {e}"))?;
-        let (max_rounded_px_w, max_rounded_px_h, max_rounded_px_count) = {
-            let mut w = 0;
-            let mut h = 0;
-            for coef in &jpeg.coefs {
-                w = w.max(coef.rounded_px_w);
-                h = h.max(coef.rounded_px_h);
+    pub fn process(mut self) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, String> {
+        let source = self.source.take().ok_or("Source is not set")?;
+        self.process_source(source)
+    }
+
+    /// Process a JPEG with the current settings, ignoring the `source` one.
+    /// Unlike [`Self::process`] this borrows the settings, so that they can be
+    /// reused across the frames of a sequence, see [`mjpeg_frames`] and
+    /// [`Sequence`], which also reuses the working buffers of the solver.
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process_source(
+        &self,
+        source: JpegSource,
+    ) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, String> {
+        let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        self.process_jpeg(jpeg, None, None, None)
+            .map(|(image, _)| image)
+    }
+
+    /// Like [`Self::process_source`], adding the time spent in each stage to
+    /// `timings`
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process_source_timed(
+        &self,
+        source: JpegSource,
+        timings: &Timings,
+    ) -> Result<image::RgbImage, String> {
+        let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        self.process_jpeg(jpeg, None, None, Some(timings))
+            .map(|(image, _)| image)
+    }
+
+    /// Process a decoded JPEG, pulled towards the output planes of the
+    /// previous frame of a sequence if given, and return the image along with
+    /// its output planes
+    fn process_jpeg(
+        &self,
+        jpeg: Jpeg,
+        previous: Option<&[Vec<f32>]>,
+        buffers: Option<&mut Vec<SolveBuffers>>,
+        timings: Option<&Timings>,
+    ) -> Result<(image::RgbImage, Vec<Vec<f32>>), String> {
+        let (max_rounded_px_w, _, _) = max_rounded_px(&jpeg.coefs);
+        let output = self.reconstruct(jpeg.coefs, previous, buffers, timings)?;
+
+        let image = timed!(
+            timings,
+            Stage::ColorConversion,
+            to_rgb(&output, jpeg.real_px_w, jpeg.real_px_h, max_rounded_px_w)
+        );
+        Ok((image, output))
+    }
+
+    /// Process the JPEG and return the reconstructed YCbCr planes, as solved:
+    /// luma is centered around 0 like chroma, and nothing is clipped.
+    /// See [`PlaneResolution`] for the size of the chroma planes.
+    /// # Errors
+    /// Returns an error if the source is not set or if reading the JPEG fails,
+    /// or with the message "BENCHMARK" if benchmarking is enabled.
+    pub fn process_planes(mut self, resolution: PlaneResolution) -> Result<Vec<Plane>, String> {
+        let source = self.source.take().ok_or("Source is not set")?;
+        self.process_source_planes(source, resolution)
+    }
+
+    /// Like [`Self::process_planes`], but borrows the settings and ignores the
+    /// `source` one, see [`Self::process_source`].
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process_source_planes(
+        &self,
+        source: JpegSource,
+        resolution: PlaneResolution,
+    ) -> Result<Vec<Plane>, String> {
+        self.source_planes(source, resolution, None)
+    }
+
+    /// Like [`Self::process_source_planes`], adding the time spent in each
+    /// stage to `timings`
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process_source_planes_timed(
+        &self,
+        source: JpegSource,
+        resolution: PlaneResolution,
+        timings: &Timings,
+    ) -> Result<Vec<Plane>, String> {
+        self.source_planes(source, resolution, Some(timings))
+    }
+
+    fn source_planes(
+        &self,
+        source: JpegSource,
+        resolution: PlaneResolution,
+        timings: Option<&Timings>,
+    ) -> Result<Vec<Plane>, String> {
+        let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        let (max_rounded_px_w, _, _) = max_rounded_px(&jpeg.coefs);
+        let sampling = sampling(&jpeg);
+        let (real_px_w, real_px_h) = (jpeg.real_px_w, jpeg.real_px_h);
+
+        let output = self.reconstruct(jpeg.coefs, None, None, timings)?;
+
+        Ok(to_planes(
+            &output,
+            max_rounded_px_w,
+            real_px_w,
+            real_px_h,
+            &sampling,
+            resolution,
+        ))
+    }
+
+    /// Like [`Self::process_source`], also returning the reconstructed YCbCr
+    /// planes, see [`Self::process_source_planes`].
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process_source_with_planes(
+        &self,
+        source: JpegSource,
+        resolution: PlaneResolution,
+    ) -> Result<(image::RgbImage, Vec<Plane>), String> {
+        let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        let (max_rounded_px_w, _, _) = max_rounded_px(&jpeg.coefs);
+        let sampling = sampling(&jpeg);
+        let (real_px_w, real_px_h) = (jpeg.real_px_w, jpeg.real_px_h);
+
+        let (image, output) = self.process_jpeg(jpeg, None, None, None)?;
+
+        Ok((
+            image,
+            to_planes(
+                &output,
+                max_rounded_px_w,
+                real_px_w,
+                real_px_h,
+                &sampling,
+                resolution,
+            ),
+        ))
+    }
+
+    /// Estimated peak memory in bytes to process a decoded JPEG with the
+    /// current settings, to bound how many images are processed at once
+    #[must_use]
+    pub fn memory_estimate(&self, jpeg: &Jpeg) -> usize {
+        self.estimate(
+            jpeg.coefs.iter().map(|coef| (coef.block_w, coef.block_h)),
+            jpeg.real_px_w,
+            jpeg.real_px_h,
+        )
+    }
+
+    /// Like [`Self::memory_estimate`], from the headers of a JPEG read with
+    /// [`jpeg_info`], to wait for the memory before decoding it
+    #[must_use]
+    pub fn memory_estimate_from_headers(&self, info: &JpegInfo) -> usize {
+        self.estimate(
+            info.components
+                .iter()
+                .map(|component| (component.block_w, component.block_h)),
+            info.width,
+            info.height,
+        )
+    }
+
+    /// Estimated peak memory for components of `(block_w, block_h)` blocks
+    /// and an image of `real_px_w` x `real_px_h` pixels
+    fn estimate(
+        &self,
+        blocks: impl Iterator<Item = (u32, u32)> + Clone,
+        real_px_w: u32,
+        real_px_h: u32,
+    ) -> usize {
+        let nchannel = blocks.clone().count();
+        let (max_block_w, max_block_h) =
+            blocks.clone().fold((0, 0), |(w, h), (block_w, block_h)| {
+                (w.max(block_w), h.max(block_h))
+            });
+        let max_rounded_px_count = max_block_w as usize * max_block_h as usize * 64;
+        let coefs = blocks
+            .map(|(block_w, block_h)| block_w as usize * block_h as usize * 64)
+            .sum::<usize>()
+            * size_of::<f32>();
+
+        // Per pixel and component: fdata and the objective gradient, plus the
+        // cosines, FISTA and pixel difference buffers that may be narrowed
+        let per_px = match self.precision {
+            _ if self.fast => 2 * size_of::<f32>(),
+            Precision::Single => 6 * size_of::<f32>(),
+            Precision::Half => 2 * size_of::<f32>() + 4 * size_of::<half::f16>(),
+            Precision::Double => 6 * size_of::<f64>(),
+        };
+
+        // Solver output and RGB image
+        let output = nchannel * max_rounded_px_count * size_of::<f32>()
+            + real_px_w as usize * real_px_h as usize * 3;
+
+        coefs + nchannel * max_rounded_px_count * per_px + output
+    }
+
+    /// Run the fast deblocking or the solver, see [`Self::solve`]
+    fn reconstruct(
+        &self,
+        coefs: Vec<Coefficient>,
+        previous: Option<&[Vec<f32>]>,
+        buffers: Option<&mut Vec<SolveBuffers>>,
+        timings: Option<&Timings>,
+    ) -> Result<Vec<Vec<f32>>, String> {
+        let (max_rounded_px_w, max_rounded_px_h, max_rounded_px_count) = max_rounded_px(&coefs);
+
+        let output = if self.fast {
+            deblock(
+                coefs,
+                max_rounded_px_w,
+                max_rounded_px_h,
+                max_rounded_px_count,
+            )
+        } else {
+            self.solve(coefs, self.pyramid, previous, buffers, timings)
+        };
+
+        if self.benchmark {
+            return Err("BENCHMARK".to_string());
+        }
+        Ok(output)
+    }
+
+    /// Run the solver on all components, after solving a half resolution
+    /// version of the problem `pyramid` times to get a better starting point.
+    /// Levels starting from a coarser solution run half of the iterations.
+    /// With the output planes of the previous frame of a sequence, starts
+    /// from them instead if `warm_start` is set, and adds the temporal term.
+    /// Reuses `buffers`, one per solve of the full resolution level, if given.
+    fn solve(
+        &self,
+        coefs: Vec<Coefficient>,
+        pyramid: usize,
+        previous: Option<&[Vec<f32>]>,
+        buffers: Option<&mut Vec<SolveBuffers>>,
+        timings: Option<&Timings>,
+    ) -> Vec<Vec<f32>> {
+        let (max_rounded_px_w, max_rounded_px_h, max_rounded_px_count) = max_rounded_px(&coefs);
+
+        // Coarse to fine
+        let coarse_init = previous.is_none_or(|_| !self.warm_start)
+            && pyramid > 0
+            && coefs.iter().all(|c| c.block_w > 1 && c.block_h > 1);
+        let init = match previous {
+            Some(previous) if self.warm_start => Some(previous.to_vec()),
+            _ if coarse_init => {
+                let coarse = utils::pyramid::downsample(&coefs);
+                let (coarse_px_w, coarse_px_h, _) = max_rounded_px(&coarse);
+
+                Some(
+                    self.solve(coarse, pyramid - 1, None, None, timings)
+                        .into_par_iter()
+                        .map(|fdata| {
+                            utils::pyramid::upsample(
+                                &fdata,
+                                coarse_px_w,
+                                coarse_px_h,
+                                max_rounded_px_w,
+                                max_rounded_px_h,
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
             }
-            (w, h, (w * h) as usize)
+            _ => None,
         };
 
         let weight = self.weight.to_slice();
         let pweight = self.pweight.to_slice();
-        let iterations = self.iterations.to_slice();
+        let iterations = self
+            .iterations
+            .to_slice()
+            .map(|iterations| utils::pyramid::level_iterations(iterations, coarse_init));
+        let temporal = previous
+            .filter(|_| self.temporal_weight != 0.0)
+            .map(|previous| Temporal {
+                previous,
+                weight: self.temporal_weight,
+            });
 
-        let mut output = if jpeg.nchannel == 3 && !self.separate_components {
-            compute(
+        // One solve for all the components together, or one per component
+        let joint = coefs.len() == 3 && !self.separate_components;
+        let solves = if joint { 1 } else { coefs.len() };
+        let mut buffers = buffers.map_or_else(
+            || (0..solves).map(|_| None).collect::<Vec<_>>(),
+            |buffers| {
+                buffers.resize_with(solves, SolveBuffers::default);
+                buffers.iter_mut().map(Some).collect()
+            },
+        );
+
+        if joint {
+            return self.run_pipeline(
                 3,
-                jpeg.coefs,
+                coefs,
                 weight[0],
                 pweight,
+                self.chroma_guide,
                 iterations[0],
+                init,
+                buffers.pop().flatten(),
+                temporal,
+                timings,
                 max_rounded_px_w,
                 max_rounded_px_h,
                 max_rounded_px_count,
-            )
-        } else {
-            // Process channels separately
-            jpeg.coefs
-                .into_par_iter()
-                .enumerate()
-                .map(|(c, coef)| {
-                    std::mem::take(
-                        &mut compute(
-                            1,
-                            vec![coef],
-                            weight[c],
-                            pweight,
-                            iterations[c],
-                            max_rounded_px_w,
-                            max_rounded_px_h,
-                            max_rounded_px_count,
-                        )[0],
-                    )
-                })
-                .collect::<Vec<_>>()
-        };
+            );
+        }
 
-        if self.benchmark {
-            return Err("BENCHMARK".to_string());
+        // Process channels separately
+        let mut inits = init.map_or_else(
+            || (0..coefs.len()).map(|_| None).collect::<Vec<_>>(),
+            |init| init.into_iter().map(Some).collect(),
+        );
+
+        coefs
+            .into_par_iter()
+            .zip(inits.par_iter_mut())
+            .zip(buffers)
+            .enumerate()
+            .map(|(c, ((coef, init), buffers))| {
+                std::mem::take(
+                    &mut self.run_pipeline(
+                        1,
+                        vec![coef],
+                        weight[c],
+                        pweight,
+                        0.0,
+                        iterations[c],
+                        init.take().map(|init| vec![init]),
+                        buffers,
+                        temporal.map(|temporal| Temporal {
+                            previous: std::slice::from_ref(&temporal.previous[c]),
+                            weight: temporal.weight,
+                        }),
+                        timings,
+                        max_rounded_px_w,
+                        max_rounded_px_h,
+                        max_rounded_px_count,
+                    )[0],
+                )
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Run the pipeline matching the selected precision
+    fn run_pipeline(
+        &self,
+        nchannel: usize,
+        coefs: Vec<Coefficient>,
+        weight: f32,
+        pweight: [f32; 3],
+        chroma_guide: f32,
+        iterations: usize,
+        init: Option<Vec<Vec<f32>>>,
+        buffers: Option<&mut SolveBuffers>,
+        temporal: Option<Temporal>,
+        timings: Option<&Timings>,
+        max_rounded_px_w: u32,
+        max_rounded_px_h: u32,
+        max_rounded_px_count: usize,
+    ) -> Vec<Vec<f32>> {
+        match self.precision {
+            Precision::Single => compute::<f32>(
+                nchannel,
+                coefs,
+                weight,
+                pweight,
+                chroma_guide,
+                iterations,
+                init,
+                buffers.map(|buffers| &mut buffers.single),
+                temporal,
+                timings,
+                max_rounded_px_w,
+                max_rounded_px_h,
+                max_rounded_px_count,
+            ),
+            Precision::Double => pipeline_reference::compute::<f64>(
+                nchannel,
+                coefs,
+                weight,
+                pweight,
+                chroma_guide,
+                iterations,
+                init,
+                temporal,
+                timings,
+                max_rounded_px_w,
+                max_rounded_px_h,
+                max_rounded_px_count,
+            ),
+            Precision::Half => compute::<half::f16>(
+                nchannel,
+                coefs,
+                weight,
+                pweight,
+                chroma_guide,
+                iterations,
+                init,
+                buffers.map(|buffers| &mut buffers.half),
+                temporal,
+                timings,
+                max_rounded_px_w,
+                max_rounded_px_h,
+                max_rounded_px_count,
+            ),
         }
+    }
+}
+
+/// Processes the frames of a video or a burst in order with one set of
+/// settings, carrying each solution over to the next frame
+///
+/// Frames solved independently each converge to a slightly different point
+/// of their quantization box, which shows up as flicker. Set `temporal_weight`
+/// on the [`Artefact`] to pull every frame towards the previous output, and
+/// `warm_start` to start the solver from it. The previous output is ignored
+/// when the frame size or component count changes.
+///
+/// The converted coefficients and working buffers of the solver are kept too,
+/// and reused by the next frame when it has the same geometry. Only the output
+/// planes are allocated for every frame, along with the buffers of the coarse
+/// `pyramid` levels, of the `fast` mode and of [`Precision::Double`].
+#[derive(Debug)]
+pub struct Sequence {
+    artefact: Artefact,
 
-        // Fixup luma range for first channel
-        for item in output[0].iter_mut().take(max_rounded_px_count) {
-            *item += 128.0;
+    /// Output planes of the previous frame, and their rounded size
+    previous: Option<(u32, u32, Vec<Vec<f32>>)>,
+
+    /// Working buffers of the solves of the previous frame
+    buffers: Vec<SolveBuffers>,
+}
+
+impl Sequence {
+    #[must_use]
+    pub const fn new(artefact: Artefact) -> Self {
+        Self {
+            artefact,
+            previous: None,
+            buffers: Vec::new(),
         }
+    }
 
-        // YCbCr -> RGB
-        if jpeg.nchannel == 3 {
-            let mut rgb: Vec<[u8; 3]> =
-                Vec::with_capacity((jpeg.real_px_h * jpeg.real_px_w) as usize);
-            for i in 0..jpeg.real_px_h {
-                for j in 0..jpeg.real_px_w {
-                    let idx = (i * max_rounded_px_w + j) as usize;
-
-                    let yi = output[0][idx];
-                    let cbi = output[1][idx];
-                    let cri = output[2][idx];
-
-                    rgb.push([
-                        mul_add!(1.402_f32, cri, yi).clamp(0.0, 255.0) as u8,
-                        mul_add!(0.71414_f32, -cri, mul_add!(0.34414_f32, -cbi, yi))
-                            .clamp(0.0, 255.0) as u8,
-                        mul_add!(1.772_f32, cbi, yi).clamp(0.0, 255.0) as u8,
-                    ]);
-                }
-            }
+    /// Settings used for every frame
+    #[must_use]
+    pub const fn artefact(&self) -> &Artefact {
+        &self.artefact
+    }
+
+    /// Process the next frame
+    /// # Errors
+    /// Returns an error if reading the JPEG fails, or with the message
+    /// "BENCHMARK" if benchmarking is enabled.
+    pub fn process(
+        &mut self,
+        source: JpegSource,
+    ) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, String> {
+        let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+        let (max_rounded_px_w, max_rounded_px_h, _) = max_rounded_px(&jpeg.coefs);
+
+        let previous = self
+            .previous
+            .as_ref()
+            .filter(|(w, h, planes)| {
+                (*w, *h) == (max_rounded_px_w, max_rounded_px_h) && planes.len() == jpeg.coefs.len()
+            })
+            .map(|(_, _, planes)| planes.as_slice());
+
+        let (image, output) =
+            self.artefact
+                .process_jpeg(jpeg, previous, Some(&mut self.buffers), None)?;
+        self.previous = Some((max_rounded_px_w, max_rounded_px_h, output));
+        Ok(image)
+    }
+
+    /// Forget the previous frame, e.g. at a scene cut
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
+
+/// Working buffers of one solve, in the storage type of each precision, see
+/// [`Sequence`]
+#[derive(Debug, Default)]
+struct SolveBuffers {
+    single: Buffers<f32>,
+    half: Buffers<half::f16>,
+}
+
+/// Largest rounded pixel size among the components, and its pixel count
+fn max_rounded_px(coefs: &[Coefficient]) -> (u32, u32, usize) {
+    let mut w = 0;
+    let mut h = 0;
+    for coef in coefs {
+        w = w.max(coef.rounded_px_w);
+        h = h.max(coef.rounded_px_h);
+    }
+    (w, h, (w * h) as usize)
+}
 
-            return Ok(image::RgbImage::from_fn(
-                jpeg.real_px_w,
-                jpeg.real_px_h,
-                |x, y| {
-                    let i = (y * jpeg.real_px_w + x) as usize;
-                    image::Rgb(rgb[i])
+/// Sampling factors of the components of a JPEG
+fn sampling(jpeg: &Jpeg) -> Vec<(SampleFactor, SampleFactor)> {
+    jpeg.coefs
+        .iter()
+        .map(|coef| (coef.horizontal_samp_factor, coef.vertical_samp_factor))
+        .collect()
+}
+
+/// Crop the solver outputs to the image size, at the `resolution` of the
+/// components
+fn to_planes(
+    output: &[Vec<f32>],
+    max_rounded_px_w: u32,
+    real_px_w: u32,
+    real_px_h: u32,
+    sampling: &[(SampleFactor, SampleFactor)],
+    resolution: PlaneResolution,
+) -> Vec<Plane> {
+    output
+        .iter()
+        .zip(sampling)
+        .map(|(fdata, sampling)| {
+            Plane::from_fdata(
+                fdata,
+                max_rounded_px_w,
+                real_px_w,
+                real_px_h,
+                match resolution {
+                    PlaneResolution::Full => (1, 1),
+                    PlaneResolution::Native => (sampling.0.u32(), sampling.1.u32()),
                 },
-            ));
-        }
+            )
+        })
+        .collect()
+}
+
+/// Convert output planes to an RGB image, cropping the padding
+fn to_rgb(
+    output: &[Vec<f32>],
+    real_px_w: u32,
+    real_px_h: u32,
+    max_rounded_px_w: u32,
+) -> image::RgbImage {
+    // Luma is solved around 0
+    let luma = |idx: usize| output[0][idx] + 128.0;
 
-        // Grayscale
-        let mut gray: Vec<u8> = Vec::with_capacity((jpeg.real_px_h * jpeg.real_px_w) as usize);
-        for i in 0..jpeg.real_px_h {
-            for j in 0..jpeg.real_px_w {
+    // YCbCr -> RGB
+    if output.len() == 3 {
+        let mut rgb: Vec<[u8; 3]> = Vec::with_capacity((real_px_h * real_px_w) as usize);
+        for i in 0..real_px_h {
+            for j in 0..real_px_w {
                 let idx = (i * max_rounded_px_w + j) as usize;
-                gray.push(output[0][idx].clamp(0.0, 255.0) as u8);
+
+                let yi = luma(idx);
+                let cbi = output[1][idx];
+                let cri = output[2][idx];
+
+                rgb.push([
+                    mul_add!(1.402_f32, cri, yi).clamp(0.0, 255.0) as u8,
+                    mul_add!(0.71414_f32, -cri, mul_add!(0.34414_f32, -cbi, yi)).clamp(0.0, 255.0)
+                        as u8,
+                    mul_add!(1.772_f32, cbi, yi).clamp(0.0, 255.0) as u8,
+                ]);
             }
         }
 
-        Ok(image::RgbImage::from_fn(
-            jpeg.real_px_w,
-            jpeg.real_px_h,
-            |x, y| {
-                let i = (y * jpeg.real_px_w + x) as usize;
-                image::Rgb([gray[i], gray[i], gray[i]])
-            },
-        ))
+        return image::RgbImage::from_fn(real_px_w, real_px_h, |x, y| {
+            let i = (y * real_px_w + x) as usize;
+            image::Rgb(rgb[i])
+        });
     }
+
+    // Grayscale
+    let mut gray: Vec<u8> = Vec::with_capacity((real_px_h * real_px_w) as usize);
+    for i in 0..real_px_h {
+        for j in 0..real_px_w {
+            let idx = (i * max_rounded_px_w + j) as usize;
+            gray.push(luma(idx).clamp(0.0, 255.0) as u8);
+        }
+    }
+
+    image::RgbImage::from_fn(real_px_w, real_px_h, |x, y| {
+        let i = (y * real_px_w + x) as usize;
+        image::Rgb([gray[i], gray[i], gray[i]])
+    })
 }
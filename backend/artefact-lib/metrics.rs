@@ -0,0 +1,156 @@
+/// Side of the Gaussian window of [`ssim`]
+const SSIM_WINDOW: usize = 11;
+/// Standard deviation of the Gaussian window of [`ssim`]
+const SSIM_SIGMA: f64 = 1.5;
+
+/// Peak signal to noise ratio between two images in dB, over the three
+/// channels, infinite for identical images
+///
+/// # Errors
+/// Returns an error if the images are not of the same size.
+pub fn psnr(reference: &image::RgbImage, image: &image::RgbImage) -> Result<f64, String> {
+    check_size(reference, image)?;
+    let sum = reference
+        .as_raw()
+        .iter()
+        .zip(image.as_raw())
+        .map(|(&a, &b)| (f64::from(a) - f64::from(b)).powi(2))
+        .sum::<f64>();
+    let mse = sum / reference.as_raw().len().max(1) as f64;
+    Ok(10.0 * (255.0 * 255.0 / mse).log10())
+}
+
+/// Mean structural similarity between the luma of two images, with the usual
+/// 11x11 Gaussian window (sigma 1.5), 1 for identical images
+///
+/// # Errors
+/// Returns an error if the images are not of the same size.
+pub fn ssim(reference: &image::RgbImage, image: &image::RgbImage) -> Result<f64, String> {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    check_size(reference, image)?;
+    let (width, height) = (reference.width() as usize, reference.height() as usize);
+    let x = luma(reference);
+    let y = luma(image);
+
+    let blur = |values: Vec<f64>| gaussian_blur(&values, width, height);
+    let mu_x = blur(x.clone());
+    let mu_y = blur(y.clone());
+    let xx = blur(x.iter().map(|v| v * v).collect());
+    let yy = blur(y.iter().map(|v| v * v).collect());
+    let xy = blur(x.iter().zip(&y).map(|(a, b)| a * b).collect());
+
+    let sum = (0..width * height)
+        .map(|i| {
+            let (mx, my) = (mu_x[i], mu_y[i]);
+            let var_x = mx.mul_add(-mx, xx[i]);
+            let var_y = my.mul_add(-my, yy[i]);
+            let cov = mx.mul_add(-my, xy[i]);
+            (2.0 * mx).mul_add(my, C1) * 2.0f64.mul_add(cov, C2)
+                / (mx.mul_add(mx, my * my) + C1)
+                / (var_x + var_y + C2)
+        })
+        .sum::<f64>();
+    Ok(sum / (width * height).max(1) as f64)
+}
+
+/// BT.601 luma, as encoded in JPEG
+fn luma(image: &image::RgbImage) -> Vec<f64> {
+    image
+        .pixels()
+        .map(|px| {
+            let [r, g, b] = px.0.map(f64::from);
+            0.299f64.mul_add(r, 0.587f64.mul_add(g, 0.114 * b))
+        })
+        .collect()
+}
+
+/// Separable Gaussian blur of the SSIM window, the edges being repeated
+fn gaussian_blur(values: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let radius = SSIM_WINDOW / 2;
+    let weights = (0..SSIM_WINDOW)
+        .map(|i| (-((i as f64 - radius as f64).powi(2)) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp())
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f64>();
+    let weights = weights.iter().map(|w| w / total).collect::<Vec<_>>();
+
+    let mut rows = vec![0.0; values.len()];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * width + x] = weights
+                .iter()
+                .enumerate()
+                .map(|(k, w)| w * values[y * width + (x + k).saturating_sub(radius).min(width - 1)])
+                .sum();
+        }
+    }
+
+    let mut blurred = vec![0.0; values.len()];
+    for y in 0..height {
+        for x in 0..width {
+            blurred[y * width + x] = weights
+                .iter()
+                .enumerate()
+                .map(|(k, w)| w * rows[(y + k).saturating_sub(radius).min(height - 1) * width + x])
+                .sum();
+        }
+    }
+    blurred
+}
+
+/// Check that two images are of the same size
+pub fn check_size(a: &image::RgbImage, b: &image::RgbImage) -> Result<(), String> {
+    if a.dimensions() != b.dimensions() {
+        return Err(format!(
+            "Image size {}x{} does not match {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn psnr_of_known_error() {
+        let a = image::RgbImage::from_pixel(4, 4, image::Rgb([100, 100, 100]));
+        let b = image::RgbImage::from_pixel(4, 4, image::Rgb([101, 99, 101]));
+
+        assert!(psnr(&a, &a).expect("same size").is_infinite());
+        // Mean squared error of 1
+        let expected = 20.0 * 255.0f64.log10();
+        assert!((psnr(&a, &b).expect("same size") - expected).abs() < 1e-9);
+        assert!(psnr(&a, &image::RgbImage::new(4, 5)).is_err());
+    }
+
+    #[test]
+    fn ssim_of_similar_images() {
+        let image = image::RgbImage::from_fn(32, 24, |x, y| {
+            image::Rgb([(x * 8) as u8, (y * 10) as u8, ((x + y) * 4) as u8])
+        });
+        let noisy = image::RgbImage::from_fn(32, 24, |x, y| {
+            let px = image.get_pixel(x, y).0;
+            image::Rgb(px.map(|v| {
+                if (x + y) % 2 == 0 {
+                    v.saturating_add(12)
+                } else {
+                    v
+                }
+            }))
+        });
+        let flat = image::RgbImage::from_pixel(32, 24, image::Rgb([128, 128, 128]));
+
+        assert!((ssim(&image, &image).expect("same size") - 1.0).abs() < 1e-9);
+        let ssim_noisy = ssim(&image, &noisy).expect("same size");
+        let ssim_flat = ssim(&image, &flat).expect("same size");
+        assert!(ssim_noisy < 1.0);
+        assert!(ssim_flat < ssim_noisy);
+        assert!(ssim(&image, &image::RgbImage::new(1, 1)).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Motion JPEG demuxing
+//!
+//! AVI files are read from their `movi` lists, where every `##dc`/`##db`
+//! chunk holds one JPEG frame. Anything else (raw concatenated JPEG streams,
+//! MOV and unknown containers) is scanned for complete JPEG images, walking
+//! the marker segments so that thumbnails embedded in APP segments are not
+//! mistaken for frames.
+//!
+//! Frames usually omit the Huffman tables, the decoder falls back to the
+//! default MJPEG ones for them.
+
+use crate::embedded::scan_jpegs;
+
+/// Split a Motion JPEG stream into its frames, in stream order
+///
+/// Dropped frames (empty AVI chunks) are skipped, so the frame count may be
+/// lower than the one in the container header.
+///
+/// # Errors
+/// Returns an error if no frame is found.
+pub fn mjpeg_frames(data: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let frames = if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"AVI " {
+        avi_frames(data)
+    } else {
+        scan_jpegs(data)
+    };
+
+    if frames.is_empty() {
+        return Err("No JPEG frame found".to_string());
+    }
+    Ok(frames)
+}
+
+/// `(id, payload)` of the RIFF chunks in `data`, stops at the first truncated one
+fn riff_chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos + 8)?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let payload = data.get(pos + 8..pos + 8 + size)?;
+        // Chunks are padded to an even size
+        pos += 8 + size + (size & 1);
+        Some((&header[..4], payload))
+    })
+}
+
+/// Frames of the `movi` lists of an AVI file, including the `AVIX`
+/// extensions of `OpenDML` (AVI 2.0) files
+fn avi_frames(data: &[u8]) -> Vec<&[u8]> {
+    fn walk<'a>(data: &'a [u8], in_movi: bool, frames: &mut Vec<&'a [u8]>) {
+        for (id, payload) in riff_chunks(data) {
+            match id {
+                b"RIFF" | b"LIST" if payload.len() >= 4 => {
+                    let in_movi = in_movi || &payload[..4] == b"movi";
+                    walk(&payload[4..], in_movi, frames);
+                }
+                [_, _, b'd', b'c' | b'b'] if in_movi && payload.starts_with(&[0xFF, 0xD8]) => {
+                    frames.push(payload);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut frames = Vec::new();
+    walk(data, false, &mut frames);
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixtures;
+
+    /// A RIFF chunk, padded to an even size
+    fn chunk(id: [u8; 4], payload: &[u8]) -> Vec<u8> {
+        let size = u32::try_from(payload.len()).expect("small chunk");
+        let mut data = [id.as_slice(), &size.to_le_bytes(), payload].concat();
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn reads_avi_frames() {
+        let (first, second) = (fixtures::jpeg(16, 8), fixtures::jpeg(8, 16));
+        let movi = [
+            b"movi".as_slice(),
+            &chunk(*b"00dc", &first),
+            // Dropped frame
+            &chunk(*b"00dc", &[]),
+            &chunk(*b"01wb", b"odd"),
+            &chunk(*b"00db", &second),
+        ]
+        .concat();
+        let avi = [
+            b"AVI ".as_slice(),
+            &chunk(
+                *b"LIST",
+                &[b"hdrl".as_slice(), &chunk(*b"avih", &[0; 56])].concat(),
+            ),
+            &chunk(*b"LIST", &movi),
+        ]
+        .concat();
+        let avi = chunk(*b"RIFF", &avi);
+
+        let frames = mjpeg_frames(&avi).expect("frames");
+        assert_eq!(frames, [first.as_slice(), second.as_slice()]);
+    }
+
+    #[test]
+    fn scans_raw_streams() {
+        let thumbnail = fixtures::jpeg(8, 8);
+        let first = fixtures::with_thumbnail(&fixtures::jpeg(16, 16), &thumbnail);
+        let second = fixtures::jpeg(16, 16);
+        let stream = [first.as_slice(), &second, &second].concat();
+
+        // The thumbnail is part of the first frame, not a frame
+        let frames = mjpeg_frames(&stream).expect("frames");
+        assert_eq!(frames, [first.as_slice(), &second, &second]);
+
+        assert!(mjpeg_frames(b"RIFF\x04\0\0\0AVI ").is_err());
+        assert!(mjpeg_frames(&[]).is_err());
+    }
+}
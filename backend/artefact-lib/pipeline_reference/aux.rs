@@ -0,0 +1,57 @@
+use super::{coef::ReferenceCoef, float::Float};
+
+#[derive(Debug)]
+pub struct PixelDifference<T> {
+    pub x: Vec<T>,
+    pub y: Vec<T>,
+}
+
+/// Working buffers for each component
+#[derive(Debug)]
+pub struct Aux<T> {
+    /// DCT coefficients for `step_prob`
+    pub cos: Vec<T>,
+
+    /// Gradient (derivative) of the objective function
+    pub obj_gradient: Vec<T>,
+
+    pub pixel_diff: PixelDifference<T>,
+
+    /// Image data
+    pub fdata: Vec<T>,
+
+    /// Previous step image data for FISTA
+    pub fista: Vec<T>,
+}
+
+impl<T: Float> Aux<T> {
+    /// Init a new auxilary buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `max_rounded_px_w` - Maximum rounded pixel width of the image
+    /// * `max_rounded_px_h` - Maximum rounded pixel height of the image
+    /// * `max_rounded_px_count` - 2 above values multiplied
+    /// * `coef` - The coefficient data
+    pub fn init(
+        max_rounded_px_w: u32,
+        max_rounded_px_h: u32,
+        max_rounded_px_count: usize,
+        coef: &ReferenceCoef<T>,
+    ) -> Self {
+        let fdata = coef.get_fdata(max_rounded_px_w, max_rounded_px_h, max_rounded_px_count);
+
+        Self {
+            cos: coef.get_cos(),
+            obj_gradient: vec![T::ZERO; max_rounded_px_count],
+
+            pixel_diff: PixelDifference {
+                x: vec![T::ZERO; max_rounded_px_count],
+                y: vec![T::ZERO; max_rounded_px_count],
+            },
+
+            fista: fdata.clone(),
+            fdata,
+        }
+    }
+}
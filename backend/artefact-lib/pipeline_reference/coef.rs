@@ -0,0 +1,92 @@
+use zune_jpeg::sample_factor::SampleFactor;
+
+use super::{dct::idct8x8, float::Float};
+use crate::jpeg::Coefficient;
+
+pub struct ReferenceCoef<T> {
+    pub rounded_px_w: u32,
+    pub rounded_px_h: u32,
+
+    pub block_w: u32,
+    pub block_h: u32,
+    pub block_count: u32,
+
+    pub horizontal_samp_factor: SampleFactor,
+    pub vertical_samp_factor: SampleFactor,
+
+    pub dct_coefs: Vec<T>,
+    pub quant_table: [T; 64],
+    pub image_data: Vec<T>,
+}
+
+impl<T: Float> From<Coefficient> for ReferenceCoef<T> {
+    fn from(c: Coefficient) -> Self {
+        let dct_coefs = c.dct_coefs.into_iter().map(T::from_f32).collect::<Vec<_>>();
+        let quant_table = c.quant_table.map(T::from_f32);
+
+        // DCT coefs + quantization table -> image data
+        let mut image_data = vec![T::ZERO; c.rounded_px_count as usize];
+        for block_y in 0..c.block_h {
+            for block_x in 0..c.block_w {
+                let i = (block_y * c.block_w + block_x) as usize;
+
+                let mut block: [T; 64] =
+                    std::array::from_fn(|j| dct_coefs[i * 64 + j] * quant_table[j]);
+                idct8x8(&mut block);
+
+                for in_y in 0..8 {
+                    for in_x in 0..8 {
+                        let y = block_y * 8 + in_y;
+                        let x = block_x * 8 + in_x;
+                        image_data[(y * c.rounded_px_w + x) as usize] =
+                            block[(in_y * 8 + in_x) as usize];
+                    }
+                }
+            }
+        }
+
+        Self {
+            rounded_px_w: c.rounded_px_w,
+            rounded_px_h: c.rounded_px_h,
+            block_w: c.block_w,
+            block_h: c.block_h,
+            block_count: c.block_count,
+            horizontal_samp_factor: c.horizontal_samp_factor,
+            vertical_samp_factor: c.vertical_samp_factor,
+            dct_coefs,
+            quant_table,
+            image_data,
+        }
+    }
+}
+
+impl<T: Float> ReferenceCoef<T> {
+    pub fn get_fdata(
+        &self,
+        max_rounded_px_w: u32,
+        max_rounded_px_h: u32,
+        max_rounded_px_count: usize,
+    ) -> Vec<T> {
+        let mut fdata = vec![T::ZERO; max_rounded_px_count];
+
+        for y in 0..max_rounded_px_h as usize {
+            for x in 0..max_rounded_px_w as usize {
+                let cy =
+                    (y / self.vertical_samp_factor.usize()).min(self.rounded_px_h as usize - 1);
+                let cx =
+                    (x / self.horizontal_samp_factor.usize()).min(self.rounded_px_w as usize - 1);
+
+                fdata[y * max_rounded_px_w as usize + x] =
+                    self.image_data[cy * self.rounded_px_w as usize + cx];
+            }
+        }
+
+        fdata
+    }
+
+    pub fn get_cos(&self) -> Vec<T> {
+        (0..self.block_count as usize * 64)
+            .map(|i| self.dct_coefs[i] * self.quant_table[i % 64])
+            .collect()
+    }
+}
@@ -0,0 +1,102 @@
+use super::{
+    aux::Aux,
+    coef::ReferenceCoef,
+    dct::{dct8x8, idct8x8},
+    float::Float,
+};
+
+pub fn compute_projection<T: Float>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    aux: &mut Aux<T>,
+    coef: &ReferenceCoef<T>,
+) {
+    let h_samp = coef.horizontal_samp_factor.u32();
+    let v_samp = coef.vertical_samp_factor.u32();
+    let resample = coef.rounded_px_w != max_rounded_px_w || coef.rounded_px_h != max_rounded_px_h;
+
+    // downsample and keep the difference
+    if resample {
+        let samp_count = T::from_f32(f32::from(
+            coef.horizontal_samp_factor.u8() * coef.vertical_samp_factor.u8(),
+        ));
+
+        for cy in 0..coef.rounded_px_h {
+            for cx in 0..coef.rounded_px_w {
+                let mut mean = T::ZERO;
+                for sy in 0..v_samp {
+                    for sx in 0..h_samp {
+                        let y = cy * v_samp + sy;
+                        let x = cx * h_samp + sx;
+                        mean += aux.fdata[(y * max_rounded_px_w + x) as usize];
+                    }
+                }
+                mean = mean / samp_count;
+
+                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = mean;
+
+                for sy in 0..v_samp {
+                    for sx in 0..h_samp {
+                        let y = cy * v_samp + sy;
+                        let x = cx * h_samp + sx;
+                        aux.fdata[(y * max_rounded_px_w + x) as usize] -= mean;
+                    }
+                }
+            }
+        }
+    }
+
+    let plane = if resample {
+        &mut aux.pixel_diff.y
+    } else {
+        &mut aux.fdata
+    };
+
+    // Project each block onto its DCT box
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            let i = (block_y * coef.block_w + block_x) as usize;
+            let px_idx = |j: usize| {
+                let y = block_y * 8 + (j / 8) as u32;
+                let x = block_x * 8 + (j % 8) as u32;
+                (y * coef.rounded_px_w + x) as usize
+            };
+
+            let mut block: [T; 64] = std::array::from_fn(|j| plane[px_idx(j)]);
+            dct8x8(&mut block);
+
+            // Clamp DCT coefficients
+            let half = T::from_f32(0.5);
+            for (j, value) in block.iter_mut().enumerate() {
+                let dct_coef = coef.dct_coefs[i * 64 + j];
+                let min = (dct_coef - half) * coef.quant_table[j];
+                let max = (dct_coef + half) * coef.quant_table[j];
+                *value = value.clmp(min, max);
+            }
+
+            // Save a copy of the DCT values for step_prob
+            aux.cos[i * 64..(i + 1) * 64].copy_from_slice(&block);
+
+            idct8x8(&mut block);
+            for (j, value) in block.into_iter().enumerate() {
+                plane[px_idx(j)] = value;
+            }
+        }
+    }
+
+    // Add back the difference
+    if resample {
+        for cy in 0..coef.rounded_px_h {
+            for cx in 0..coef.rounded_px_w {
+                let mean = aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize];
+                for sy in 0..v_samp {
+                    for sx in 0..h_samp {
+                        let y = cy * v_samp + sy;
+                        let x = cx * h_samp + sx;
+                        aux.fdata[(y * max_rounded_px_w + x) as usize] += mean;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::{
+    aux::Aux, coef::ReferenceCoef, compute_projection::compute_projection,
+    compute_step_chroma_guide::compute_step_chroma_guide, compute_step_prob::compute_step_prob,
+    compute_step_temporal::compute_step_temporal, compute_step_tv::compute_step_tv,
+    compute_step_tv2::compute_step_tv2, float::Float,
+};
+use crate::utils::{
+    temporal::Temporal,
+    timing::{Stage, Timings, timed},
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn compute_step<T: Float>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    max_rounded_px_count: usize,
+    nchannel: usize,
+    coefs: &[ReferenceCoef<T>],
+    auxs: &mut [Aux<T>],
+    step_size: T,
+    weight: T,
+    pweight: &[T; 3],
+    chroma_guide: T,
+    temporal: Option<Temporal>,
+    timings: Option<&Timings>,
+) {
+    let sqrt_2 = T::from_f32(2.0).sqrt();
+
+    for c in 0..nchannel {
+        let aux = &mut auxs[c];
+
+        aux.obj_gradient = vec![T::ZERO; max_rounded_px_count];
+
+        // DCT coefficient distance
+        if pweight[c] != T::ZERO {
+            timed!(
+                timings,
+                Stage::Prob,
+                compute_step_prob(
+                    max_rounded_px_w,
+                    pweight[c] * T::from_f32(2.0 * 255.0) * sqrt_2,
+                    &coefs[c],
+                    &aux.cos,
+                    &mut aux.obj_gradient,
+                )
+            );
+        }
+    }
+
+    // TV computation
+    timed!(
+        timings,
+        Stage::Tv,
+        compute_step_tv(max_rounded_px_w, max_rounded_px_h, nchannel, auxs)
+    );
+
+    // TGV second order
+    timed!(
+        timings,
+        Stage::Tgv,
+        compute_step_tv2(
+            max_rounded_px_w,
+            max_rounded_px_h,
+            nchannel,
+            auxs,
+            weight / sqrt_2,
+        )
+    );
+
+    // Luma-guided chroma
+    if nchannel == 3 && chroma_guide != T::ZERO {
+        compute_step_chroma_guide(max_rounded_px_w, max_rounded_px_h, auxs, chroma_guide);
+    }
+
+    // Temporal smoothness
+    if let Some(temporal) = temporal {
+        compute_step_temporal(auxs, temporal);
+    }
+
+    // Gradient descent step along the normalized objective gradient
+    for aux in auxs.iter_mut() {
+        let norm = aux.obj_gradient.iter().map(|&x| x * x).sum::<T>().sqrt();
+
+        if norm != T::ZERO {
+            for (fdata, &gradient) in aux.fdata.iter_mut().zip(&aux.obj_gradient) {
+                *fdata -= step_size * (gradient / norm);
+            }
+        }
+    }
+
+    // Project onto DCT basis
+    for (aux, coef) in auxs.iter_mut().zip(coefs) {
+        timed!(
+            timings,
+            Stage::Projection,
+            compute_projection(max_rounded_px_w, max_rounded_px_h, aux, coef)
+        );
+    }
+}
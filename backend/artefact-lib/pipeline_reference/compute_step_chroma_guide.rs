@@ -0,0 +1,92 @@
+use super::{aux::Aux, float::Float};
+use crate::utils::guide::EDGE_SCALE;
+
+/// Computes the luma-guided chroma regularization term and its gradient, see
+/// [`crate::utils::guide::compute_step_chroma_guide`]
+pub fn compute_step_chroma_guide<T: Float>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    auxs: &mut [Aux<T>],
+    alpha: T,
+) {
+    let edge_scale_sq = T::from_f32(EDGE_SCALE * EDGE_SCALE);
+
+    for curr_y in 0..max_rounded_px_h {
+        for curr_x in 0..max_rounded_px_w {
+            let curr_px_idx = (curr_y * max_rounded_px_w + curr_x) as usize;
+            let next_px_idx = curr_px_idx + 1;
+            let below_px_idx = curr_px_idx + max_rounded_px_w as usize;
+
+            let px_at_right_edge = curr_x >= max_rounded_px_w - 1;
+            let px_at_bottom_edge = curr_y >= max_rounded_px_h - 1;
+
+            let forward_diff = |fdata: &[T]| {
+                (
+                    if px_at_right_edge {
+                        T::ZERO
+                    } else {
+                        fdata[next_px_idx] - fdata[curr_px_idx]
+                    },
+                    if px_at_bottom_edge {
+                        T::ZERO
+                    } else {
+                        fdata[below_px_idx] - fdata[curr_px_idx]
+                    },
+                )
+            };
+
+            // luma edge strength and direction
+            let (luma_x, luma_y) = forward_diff(&auxs[0].fdata);
+            let luma_norm_sq = luma_x * luma_x + luma_y * luma_y;
+            let flatness = T::ONE / (T::ONE + luma_norm_sq / edge_scale_sq);
+
+            // unit tangent of the luma edge
+            let (tan_x, tan_y) = if luma_norm_sq == T::ZERO {
+                (T::ZERO, T::ZERO)
+            } else {
+                let luma_norm = luma_norm_sq.sqrt();
+                (-luma_y / luma_norm, luma_x / luma_norm)
+            };
+
+            // metric = flatness * I + (1 - flatness) * t * t^T
+            let metric = |g_x: T, g_y: T| {
+                let along = (g_x * tan_x + g_y * tan_y) * (T::ONE - flatness);
+                (
+                    flatness * g_x + along * tan_x,
+                    flatness * g_y + along * tan_y,
+                )
+            };
+
+            let mut g_xs = [T::ZERO; 2];
+            let mut g_ys = [T::ZERO; 2];
+            let mut m_xs = [T::ZERO; 2];
+            let mut m_ys = [T::ZERO; 2];
+            let mut g_norm = T::ZERO;
+            for c in 0..2 {
+                (g_xs[c], g_ys[c]) = forward_diff(&auxs[c + 1].fdata);
+                (m_xs[c], m_ys[c]) = metric(g_xs[c], g_ys[c]);
+                g_norm += g_xs[c] * m_xs[c] + g_ys[c] * m_ys[c];
+            }
+            let g_norm = g_norm.sqrt();
+
+            if g_norm == T::ZERO {
+                continue;
+            }
+
+            // compute derivatives
+            for c in 0..2 {
+                let aux = &mut auxs[c + 1];
+
+                aux.obj_gradient[curr_px_idx] += alpha * -(m_xs[c] + m_ys[c]) / g_norm;
+
+                if !px_at_right_edge {
+                    aux.obj_gradient[next_px_idx] += alpha * m_xs[c] / g_norm;
+                }
+
+                if !px_at_bottom_edge {
+                    aux.obj_gradient[below_px_idx] += alpha * m_ys[c] / g_norm;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use super::{coef::ReferenceCoef, dct::idct8x8, float::Float};
+
+/// Compute objective gradient for the distance of DCT coefficients from normal decoding
+pub fn compute_step_prob<T: Float>(
+    max_rounded_px_w: u32,
+    alpha: T,
+    coef: &ReferenceCoef<T>,
+    cos: &[T],
+    obj_gradient: &mut [T],
+) {
+    let h_samp = coef.horizontal_samp_factor.u32();
+    let v_samp = coef.vertical_samp_factor.u32();
+
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            let i = (block_y * coef.block_w + block_x) as usize;
+
+            // derivative of the distance, in the DCT domain
+            let mut cosbs: [T; 64] = std::array::from_fn(|j| {
+                let quant = coef.quant_table[j];
+                (cos[i * 64 + j] - coef.dct_coefs[i * 64 + j] * quant) / (quant * quant)
+            });
+
+            idct8x8(&mut cosbs);
+
+            // Distribute gradient to output buffer with upsampling
+            for (j, cosb) in cosbs.into_iter().enumerate() {
+                let cx = block_x * 8 + (j % 8) as u32;
+                let cy = block_y * 8 + (j / 8) as u32;
+
+                for sy in 0..v_samp {
+                    for sx in 0..h_samp {
+                        let y = cy * v_samp + sy;
+                        let x = cx * h_samp + sx;
+                        obj_gradient[(y * max_rounded_px_w + x) as usize] += alpha * cosb;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use super::{aux::Aux, float::Float};
+use crate::utils::temporal::Temporal;
+
+/// Computes the gradient of the temporal smoothness term, see
+/// [`crate::utils::temporal::compute_step_temporal`]
+pub fn compute_step_temporal<T: Float>(auxs: &mut [Aux<T>], temporal: Temporal) {
+    let weight = T::from_f32(temporal.weight);
+
+    for (aux, previous) in auxs.iter_mut().zip(temporal.previous) {
+        for ((gradient, &fdata), &previous) in
+            aux.obj_gradient.iter_mut().zip(&aux.fdata).zip(previous)
+        {
+            *gradient += weight * (fdata - T::from_f32(previous));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use super::{aux::Aux, float::Float};
+
+/// Computes the Total Variation (TV) regularization term and its gradient
+pub fn compute_step_tv<T: Float>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    nchannel: usize,
+    auxs: &mut [Aux<T>],
+) {
+    let alpha = T::ONE / T::from_f32(nchannel as f32).sqrt();
+
+    for curr_y in 0..max_rounded_px_h {
+        for curr_x in 0..max_rounded_px_w {
+            let mut g_xs = [T::ZERO; 3];
+            let mut g_ys = [T::ZERO; 3];
+
+            let curr_px_idx = (curr_y * max_rounded_px_w + curr_x) as usize;
+            let next_px_idx = curr_px_idx + 1;
+            let below_px_idx = curr_px_idx + max_rounded_px_w as usize;
+
+            let px_at_right_edge = curr_x >= max_rounded_px_w - 1;
+            let px_at_bottom_edge = curr_y >= max_rounded_px_h - 1;
+
+            // forward differences
+            for c in 0..nchannel {
+                let aux = &auxs[c];
+                if !px_at_right_edge {
+                    g_xs[c] = aux.fdata[next_px_idx] - aux.fdata[curr_px_idx];
+                }
+                if !px_at_bottom_edge {
+                    g_ys[c] = aux.fdata[below_px_idx] - aux.fdata[curr_px_idx];
+                }
+            }
+
+            // norm
+            let g_norm = (0..nchannel)
+                .map(|c| g_xs[c] * g_xs[c] + g_ys[c] * g_ys[c])
+                .sum::<T>()
+                .sqrt();
+
+            // compute derivatives
+            if g_norm != T::ZERO {
+                for c in 0..nchannel {
+                    let aux = &mut auxs[c];
+
+                    aux.obj_gradient[curr_px_idx] += alpha * -(g_xs[c] + g_ys[c]) / g_norm;
+
+                    if !px_at_right_edge {
+                        aux.obj_gradient[next_px_idx] += alpha * g_xs[c] / g_norm;
+                    }
+
+                    if !px_at_bottom_edge {
+                        aux.obj_gradient[below_px_idx] += alpha * g_ys[c] / g_norm;
+                    }
+                }
+            }
+
+            // store for use in tv2
+            for c in 0..nchannel {
+                auxs[c].pixel_diff.x[curr_px_idx] = g_xs[c];
+                auxs[c].pixel_diff.y[curr_px_idx] = g_ys[c];
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{aux::Aux, float::Float};
+
+/// Computes the Total Generalized Variation (TGV) regularization term and its gradient
+pub fn compute_step_tv2<T: Float>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    nchannel: usize,
+    auxs: &mut [Aux<T>],
+    alpha: T,
+) {
+    let alpha = alpha / T::from_f32(nchannel as f32).sqrt();
+    let two = T::from_f32(2.0);
+    let half = T::from_f32(0.5);
+
+    for curr_y in 0..max_rounded_px_h {
+        for curr_x in 0..max_rounded_px_w {
+            let mut g_xxs = [T::ZERO; 3];
+            let mut g_xy_syms = [T::ZERO; 3];
+            let mut g_yys = [T::ZERO; 3];
+
+            let curr = (curr_y * max_rounded_px_w + curr_x) as usize;
+            let w = max_rounded_px_w as usize;
+
+            for c in 0..nchannel {
+                let aux = &auxs[c];
+                let mut g_xy = T::ZERO;
+                let mut g_yx = T::ZERO;
+
+                // backward difference x
+                if curr_x != 0 {
+                    g_xxs[c] = aux.pixel_diff.x[curr] - aux.pixel_diff.x[curr - 1];
+                    g_yx = aux.pixel_diff.y[curr] - aux.pixel_diff.y[curr - 1];
+                }
+
+                // backward difference y
+                if curr_y != 0 {
+                    g_yys[c] = aux.pixel_diff.y[curr] - aux.pixel_diff.y[curr - w];
+                    g_xy = aux.pixel_diff.x[curr] - aux.pixel_diff.x[curr - w];
+                }
+
+                // symmetrize
+                g_xy_syms[c] = (g_xy + g_yx) * half;
+            }
+
+            // norm
+            let g2_norm = (0..nchannel)
+                .map(|c| {
+                    g_xxs[c] * g_xxs[c] + two * g_xy_syms[c] * g_xy_syms[c] + g_yys[c] * g_yys[c]
+                })
+                .sum::<T>()
+                .sqrt();
+
+            if g2_norm == T::ZERO {
+                continue;
+            }
+
+            // compute derivatives
+            for c in 0..nchannel {
+                let g_xx = g_xxs[c];
+                let g_yy = g_yys[c];
+                let g_xy_sym = g_xy_syms[c];
+                let obj_gradient = &mut auxs[c].obj_gradient;
+
+                obj_gradient[curr] +=
+                    alpha * (-(two * g_yy + two * g_xx + two * g_xy_sym) / g2_norm);
+
+                if curr_x > 0 {
+                    obj_gradient[curr - 1] += alpha * ((g_xy_sym + g_xx) / g2_norm);
+                }
+
+                if curr_x < max_rounded_px_w - 1 {
+                    obj_gradient[curr + 1] += alpha * ((g_xy_sym + g_xx) / g2_norm);
+                }
+
+                if curr_y > 0 {
+                    obj_gradient[curr - w] += alpha * ((g_yy + g_xy_sym) / g2_norm);
+                }
+
+                if curr_y < max_rounded_px_h - 1 {
+                    obj_gradient[curr + w] += alpha * ((g_yy + g_xy_sym) / g2_norm);
+                }
+
+                if curr_x < max_rounded_px_w - 1 && curr_y > 0 {
+                    obj_gradient[curr - w + 1] += alpha * ((-g_xy_sym) / g2_norm);
+                }
+
+                if curr_x > 0 && curr_y < max_rounded_px_h - 1 {
+                    obj_gradient[curr + w - 1] += alpha * ((-g_xy_sym) / g2_norm);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::{f64::consts::PI, sync::LazyLock};
+
+use super::float::Float;
+
+/// Orthonormal 8-point DCT-II basis, `BASIS[k][n]` for frequency `k` and sample `n`
+static BASIS: LazyLock<[[f64; 8]; 8]> = LazyLock::new(|| {
+    std::array::from_fn(|k| {
+        let scale = if k == 0 { (1.0 / 8.0_f64).sqrt() } else { 0.5 };
+        std::array::from_fn(|n| scale * (PI * (2 * n + 1) as f64 * k as f64 / 16.0).cos())
+    })
+});
+
+fn basis<T: Float>() -> [[T; 8]; 8] {
+    BASIS.map(|row| row.map(T::from_f64))
+}
+
+/// Discrete Cosine Transform (DCT) for 8x8 blocks, by definition
+pub fn dct8x8<T: Float>(a: &mut [T; 64]) {
+    let basis = basis::<T>();
+    let mut tmp = [T::ZERO; 64];
+
+    // rows
+    for y in 0..8 {
+        for k in 0..8 {
+            tmp[y * 8 + k] = (0..8).map(|n| basis[k][n] * a[y * 8 + n]).sum();
+        }
+    }
+
+    // columns
+    for x in 0..8 {
+        for k in 0..8 {
+            a[k * 8 + x] = (0..8).map(|n| basis[k][n] * tmp[n * 8 + x]).sum();
+        }
+    }
+}
+
+/// Inverse Discrete Cosine Transform (IDCT) for 8x8 blocks, by definition
+pub fn idct8x8<T: Float>(a: &mut [T; 64]) {
+    let basis = basis::<T>();
+    let mut tmp = [T::ZERO; 64];
+
+    // rows
+    for y in 0..8 {
+        for n in 0..8 {
+            tmp[y * 8 + n] = (0..8).map(|k| basis[k][n] * a[y * 8 + k]).sum();
+        }
+    }
+
+    // columns
+    for x in 0..8 {
+        for n in 0..8 {
+            a[n * 8 + x] = (0..8).map(|k| basis[k][n] * tmp[k * 8 + x]).sum();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    fmt::Debug,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+/// Floating point type the reference pipeline can run with
+pub trait Float:
+    Copy
+    + Debug
+    + Default
+    + PartialOrd
+    + Send
+    + Sync
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f32(v: f32) -> Self;
+    fn from_f64(v: f64) -> Self;
+    fn to_f32(self) -> f32;
+    fn sqrt(self) -> Self;
+    fn clmp(self, min: Self, max: Self) -> Self;
+}
+
+impl Float for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f32(v: f32) -> Self {
+        v
+    }
+    fn from_f64(v: f64) -> Self {
+        v as Self
+    }
+    fn to_f32(self) -> f32 {
+        self
+    }
+    fn sqrt(self) -> Self {
+        Self::sqrt(self)
+    }
+    fn clmp(self, min: Self, max: Self) -> Self {
+        self.clamp(min, max)
+    }
+}
+
+impl Float for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f32(v: f32) -> Self {
+        Self::from(v)
+    }
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn sqrt(self) -> Self {
+        Self::sqrt(self)
+    }
+    fn clmp(self, min: Self, max: Self) -> Self {
+        self.clamp(min, max)
+    }
+}
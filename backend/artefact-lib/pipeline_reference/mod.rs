@@ -0,0 +1,261 @@
+//! Reference pipeline, written for clarity instead of speed and generic over
+//! the float type. Ran with `f64` it serves as an oracle for the `f32`
+//! pipelines: on the same inputs, their output planes stay within
+//! [`TOLERANCE_RMS`] and [`TOLERANCE_MAX`] of it.
+
+mod aux;
+mod coef;
+mod compute_projection;
+mod compute_step;
+mod compute_step_chroma_guide;
+mod compute_step_prob;
+mod compute_step_temporal;
+mod compute_step_tv;
+mod compute_step_tv2;
+mod dct;
+mod float;
+
+pub use float::Float;
+
+use crate::{
+    jpeg::Coefficient,
+    utils::{
+        temporal::Temporal,
+        timing::{Stage, Timings, timed},
+    },
+};
+use aux::Aux;
+use coef::ReferenceCoef;
+use compute_step::compute_step;
+
+/// Root mean square difference (0-255 scale) expected between the output planes
+/// of an `f32` pipeline and the `f64` reference pipeline on the same inputs
+///
+/// Rounding differences change which way the TV terms push nearly flat areas,
+/// so the two drift apart slowly with the iteration count. Typical photos stay
+/// around 0.15 after 50 iterations.
+pub const TOLERANCE_RMS: f32 = 0.25;
+
+/// Largest difference in a single value (0-255 scale) expected between the
+/// output planes of an `f32` pipeline and the `f64` reference pipeline
+///
+/// Reached in the few pixels where a TV term flips direction, typical photos
+/// stay under 3.5 after 50 iterations.
+pub const TOLERANCE_MAX: f32 = 4.0;
+
+pub fn compute<T: Float>(
+    nchannel: usize,
+    coefs: Vec<Coefficient>,
+    weight: f32,
+    pweight: [f32; 3],
+    chroma_guide: f32,
+    iterations: usize,
+    init: Option<Vec<Vec<f32>>>,
+    temporal: Option<Temporal>,
+    timings: Option<&Timings>,
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    max_rounded_px_count: usize,
+) -> Vec<Vec<f32>> {
+    let coefs = timed!(
+        timings,
+        Stage::CoefConversion,
+        coefs
+            .into_iter()
+            .map(ReferenceCoef::<T>::from)
+            .collect::<Vec<_>>()
+    );
+
+    // Initialize working buffers for each channel
+    let mut auxs = timed!(
+        timings,
+        Stage::AuxInit,
+        coefs
+            .iter()
+            .take(nchannel)
+            .map(|coef| {
+                Aux::init(
+                    max_rounded_px_w,
+                    max_rounded_px_h,
+                    max_rounded_px_count,
+                    coef,
+                )
+            })
+            .collect::<Vec<_>>()
+    );
+
+    // Start from a given solution instead of the plain decode
+    if let Some(init) = init {
+        for (aux, fdata) in auxs.iter_mut().zip(init) {
+            aux.fdata = fdata.into_iter().map(T::from_f32).collect();
+            aux.fista.clone_from(&aux.fdata);
+        }
+    }
+
+    // Radius of [-0.5, 0.5]^(h*w)
+    let radius = T::from_f32(max_rounded_px_count as f32).sqrt() / T::from_f32(2.0);
+    let step_size = radius / T::from_f32(1.0 + iterations as f32).sqrt();
+    let pweight = pweight.map(T::from_f32);
+    let mut term = T::ONE;
+
+    // Main iteration loop
+    for _ in 0..iterations {
+        // FISTA update
+        let next_term =
+            (T::ONE + (T::from_f32(4.0) * term * term + T::ONE).sqrt()) / T::from_f32(2.0);
+        let factor = (term - T::ONE) / next_term;
+
+        for aux in &mut auxs {
+            for (fista, &fdata) in aux.fista.iter_mut().zip(&aux.fdata) {
+                *fista = factor * (fdata - *fista) + fdata;
+            }
+            std::mem::swap(&mut aux.fdata, &mut aux.fista);
+        }
+
+        term = next_term;
+
+        // Take a step
+        compute_step(
+            max_rounded_px_w,
+            max_rounded_px_h,
+            max_rounded_px_count,
+            nchannel,
+            &coefs,
+            &mut auxs,
+            step_size,
+            T::from_f32(weight),
+            &pweight,
+            T::from_f32(chroma_guide),
+            temporal,
+            timings,
+        );
+    }
+
+    auxs.into_iter()
+        .map(|aux| aux.fdata.into_iter().map(T::to_f32).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TOLERANCE_MAX, TOLERANCE_RMS, compute};
+    use crate::jpeg::Coefficient;
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    /// Deterministic coefficients: smooth DC ramps with a few AC terms
+    fn coefficient(block_w: u32, block_h: u32, samp: SampleFactor, seed: u32) -> Coefficient {
+        let block_count = block_w * block_h;
+        let mut state = seed;
+        let mut dct_coefs = vec![0.0; block_count as usize * 64];
+        for i in 0..block_count as usize {
+            dct_coefs[i * 64] = (i % 13) as f32 - 6.0;
+            for j in [1, 8, 9, 2, 16] {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                dct_coefs[i * 64 + j] = ((state >> 16) % 5) as f32 - 2.0;
+            }
+        }
+
+        Coefficient {
+            rounded_px_w: block_w * 8,
+            rounded_px_h: block_h * 8,
+            rounded_px_count: block_count * 64,
+            block_w,
+            block_h,
+            block_count,
+            horizontal_samp_factor: samp,
+            vertical_samp_factor: samp,
+            dct_coefs,
+            quant_table: std::array::from_fn(|j| 8.0 + j as f32),
+        }
+    }
+
+    type Compute<C> = fn(
+        usize,
+        Vec<Coefficient>,
+        f32,
+        [f32; 3],
+        f32,
+        usize,
+        Option<Vec<Vec<f32>>>,
+        Option<&mut crate::utils::aux::Buffers<C, f32>>,
+        Option<crate::utils::temporal::Temporal>,
+        Option<&crate::utils::timing::Timings>,
+        u32,
+        u32,
+        usize,
+    ) -> Vec<Vec<f32>>;
+
+    /// Largest and root mean square difference between the output planes of an
+    /// `f32` pipeline and the reference pipeline
+    fn differences<C>(single: Compute<C>) -> (f32, f32) {
+        let coefs = vec![
+            coefficient(6, 4, SampleFactor::One, 1),
+            coefficient(3, 2, SampleFactor::Two, 2),
+            coefficient(3, 2, SampleFactor::Two, 3),
+        ];
+        let (w, h) = (48, 32);
+
+        let single = single(
+            3,
+            coefs.clone(),
+            0.3,
+            [0.001; 3],
+            0.5,
+            50,
+            None,
+            None,
+            None,
+            None,
+            w,
+            h,
+            (w * h) as usize,
+        );
+        let double = compute::<f64>(
+            3,
+            coefs,
+            0.3,
+            [0.001; 3],
+            0.5,
+            50,
+            None,
+            None,
+            None,
+            w,
+            h,
+            (w * h) as usize,
+        );
+
+        let diffs = single
+            .iter()
+            .flatten()
+            .zip(double.iter().flatten())
+            .map(|(a, b)| (a - b).abs())
+            .collect::<Vec<_>>();
+        let max = diffs.iter().copied().fold(0.0, f32::max);
+        let rms = (diffs.iter().map(|d| d * d).sum::<f32>() / diffs.len() as f32).sqrt();
+        (max, rms)
+    }
+
+    fn assert_agrees<C>(single: Compute<C>) {
+        let (max, rms) = differences(single);
+        assert!(max <= TOLERANCE_MAX, "max difference {max}");
+        assert!(rms <= TOLERANCE_RMS, "rms difference {rms}");
+    }
+
+    #[test]
+    fn scalar_agrees_with_reference() {
+        assert_agrees(crate::pipeline_scalar::compute::<f32>);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_8_agrees_with_reference() {
+        assert_agrees(crate::pipeline_simd_8::compute::<f32>);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_adaptive_agrees_with_reference() {
+        assert_agrees(crate::pipeline_simd_adaptive::compute::<f32>);
+    }
+}
@@ -1,9 +1,14 @@
 use crate::{
     jpeg::Coefficient,
-    utils::{aux::AuxTraits, boxing::unboxing, dct::idct8x8s},
+    utils::{
+        aux::{AuxTraits, Refill},
+        boxing::unboxing,
+        dct::idct8x8s,
+    },
 };
 use zune_jpeg::sample_factor::SampleFactor;
 
+#[derive(Debug)]
 pub struct ScalarCoef {
     pub rounded_px_w: u32,
     pub rounded_px_h: u32,
@@ -23,7 +28,21 @@ pub struct ScalarCoef {
 
 impl From<Coefficient> for ScalarCoef {
     fn from(c: Coefficient) -> Self {
-        let mut image_data = vec![0.0; c.rounded_px_count as usize];
+        Self::convert(c, Vec::new())
+    }
+}
+
+impl Refill for ScalarCoef {
+    fn refill(&mut self, c: Coefficient) {
+        *self = Self::convert(c, std::mem::take(&mut self.image_data));
+    }
+}
+
+impl ScalarCoef {
+    /// Convert `c`, decoding it into the `image_data` buffer
+    fn convert(c: Coefficient, mut image_data: Vec<f32>) -> Self {
+        image_data.clear();
+        image_data.resize(c.rounded_px_count as usize, 0.0);
 
         // DCT coefs + quantization table -> image data
         for i in 0..(c.block_count as usize) {
@@ -37,18 +56,18 @@ impl From<Coefficient> for ScalarCoef {
                     .try_into()
                     .expect("Invalid coef's image data length"),
             );
-
-            // 8x8 -> 64x1
-            unboxing(
-                &image_data.clone(),
-                image_data.as_mut(),
-                c.rounded_px_w,
-                c.rounded_px_h,
-                c.block_w,
-                c.block_h,
-            );
         }
 
+        // 8x8 -> 64x1
+        unboxing(
+            &image_data.clone(),
+            image_data.as_mut(),
+            c.rounded_px_w,
+            c.rounded_px_h,
+            c.block_w,
+            c.block_h,
+        );
+
         Self {
             rounded_px_w: c.rounded_px_w,
             rounded_px_h: c.rounded_px_h,
@@ -103,3 +122,49 @@ impl AuxTraits for ScalarCoef {
         cos
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)] // the same computation on both sides
+    fn decodes_blocks_in_raster_order() {
+        // 3x2 blocks, flat at a different level each
+        let (block_w, block_h) = (3, 2);
+        let block_count = block_w * block_h;
+        let mut dct_coefs = vec![0.0; block_count as usize * 64];
+        for i in 0..block_count as usize {
+            dct_coefs[i * 64] = i as f32 - 2.0;
+        }
+        let coef = ScalarCoef::from(Coefficient {
+            rounded_px_w: block_w * 8,
+            rounded_px_h: block_h * 8,
+            rounded_px_count: block_count * 64,
+            block_w,
+            block_h,
+            block_count,
+            horizontal_samp_factor: SampleFactor::One,
+            vertical_samp_factor: SampleFactor::One,
+            dct_coefs: dct_coefs.clone(),
+            quant_table: [16.0; 64],
+        });
+
+        let level = |block: usize| {
+            let mut data = [0.0; 64];
+            data[0] = dct_coefs[block * 64] * 16.0;
+            idct8x8s(&mut data);
+            data[0]
+        };
+        for y in 0..(block_h * 8) as usize {
+            for x in 0..(block_w * 8) as usize {
+                let block = y / 8 * block_w as usize + x / 8;
+                assert_eq!(
+                    coef.image_data[y * (block_w * 8) as usize + x],
+                    level(block),
+                    "pixel {x},{y}"
+                );
+            }
+        }
+    }
+}
@@ -1,14 +1,15 @@
 use super::ScalarCoef;
 use crate::utils::{
     aux::Aux,
-    boxing::{boxing, unboxing},
+    boxing::{box_block, unbox_block},
     dct::{dct8x8s, idct8x8s},
+    store::Widen,
 };
 
-pub fn compute_projection(
+pub fn compute_projection<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
-    aux: &mut Aux,
+    aux: &mut Aux<S>,
     coef: &ScalarCoef,
 ) {
     let resample = coef.rounded_px_w != max_rounded_px_w || coef.rounded_px_h != max_rounded_px_h;
@@ -31,7 +32,10 @@ pub fn compute_projection(
                     f32::from(coef.horizontal_samp_factor.u8() * coef.vertical_samp_factor.u8());
 
                 debug_assert!(cx < coef.rounded_px_w && cy < coef.rounded_px_h);
-                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = mean;
+                // keep exactly what gets stored, so that adding it back is lossless
+                let stored = S::narrow(mean);
+                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = stored;
+                let mean = stored.widen();
 
                 for sy in 0..coef.vertical_samp_factor.u32() {
                     for sx in 0..coef.horizontal_samp_factor.u32() {
@@ -46,69 +50,51 @@ pub fn compute_projection(
         }
     }
 
-    // Project onto DCT box
-    boxing(
-        if resample {
-            &aux.pixel_diff.y
-        } else {
-            &aux.fdata
-        },
-        aux.pixel_diff.x.as_mut(),
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
+    // Project onto DCT box, one block at a time
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            let i = (block_y * coef.block_w + block_x) as usize;
 
-    for i in 0..coef.block_count as usize {
-        dct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
-    }
+            let mut block = if resample {
+                box_block(&aux.pixel_diff.y, coef.rounded_px_w, block_x, block_y)
+            } else {
+                box_block(&aux.fdata, coef.rounded_px_w, block_x, block_y)
+            };
 
-    // Clamp DCT coefficients
-    for i in 0..coef.block_count as usize {
-        for j in 0..64 {
-            let min = (coef.dct_coefs[i * 64 + j] - 0.5) * coef.quant_table[j];
-            let max = (coef.dct_coefs[i * 64 + j] + 0.5) * coef.quant_table[j];
-            aux.pixel_diff.x[i * 64 + j] = aux.pixel_diff.x[i * 64 + j].clamp(min, max);
-        }
-    }
+            dct8x8s(&mut block);
 
-    // Save a copy of the DCT values for step_prob
-    aux.cos = aux.pixel_diff.x.clone();
+            // Clamp DCT coefficients
+            for (j, value) in block.iter_mut().enumerate() {
+                let min = (coef.dct_coefs[i * 64 + j] - 0.5) * coef.quant_table[j];
+                let max = (coef.dct_coefs[i * 64 + j] + 0.5) * coef.quant_table[j];
+                *value = value.clamp(min, max);
+            }
 
-    // add back the difference (orthogonal to our subsampling vector)
-    for i in 0..coef.block_count as usize {
-        idct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
-    }
+            // Save a copy of the DCT values for step_prob
+            S::narrow_slice(&block, &mut aux.cos[i * 64..(i + 1) * 64]);
 
-    unboxing(
-        &aux.pixel_diff.x,
-        if resample {
-            aux.pixel_diff.y.as_mut()
-        } else {
-            aux.fdata.as_mut()
-        },
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
+            // add back the difference (orthogonal to our subsampling vector)
+            idct8x8s(&mut block);
+
+            if resample {
+                unbox_block(
+                    &block,
+                    &mut aux.pixel_diff.y,
+                    coef.rounded_px_w,
+                    block_x,
+                    block_y,
+                );
+            } else {
+                unbox_block(&block, &mut aux.fdata, coef.rounded_px_w, block_x, block_y);
+            }
+        }
+    }
 
     // Add back the difference
     if resample {
         for cy in 0..coef.rounded_px_h {
             for cx in 0..coef.rounded_px_w {
-                let mean = aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize];
+                let mean = aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize].widen();
                 for sy in 0..coef.vertical_samp_factor.u32() {
                     for sx in 0..coef.horizontal_samp_factor.u32() {
                         let y = cy * coef.vertical_samp_factor.u32() + sy;
@@ -1,13 +1,16 @@
-use crate::{pipeline_scalar::coef::ScalarCoef, utils::dct::idct8x8s};
+use crate::{
+    pipeline_scalar::coef::ScalarCoef,
+    utils::{dct::idct8x8s, store::Widen},
+};
 
 // Compute objective gradient for the distance of DCT coefficients from normal decoding
 // N.B. destroys cos
-pub fn compute_step_prob(
+pub fn compute_step_prob<S: Widen>(
     max_rounded_px_w: u32,    // Maximum width after rounding to block size
     max_rounded_px_h: u32,    // Maximum height after rounding to block size
     alpha: f32,               // Learning rate parameter
     coef: &ScalarCoef,        // JPEG coefficient data
-    cos: &[f32],              // Cosine transform data
+    cos: &[S],                // Cosine transform data
     obj_gradient: &mut [f32], // Output gradient buffer
 ) {
     // Iterate through each 8x8 block in the image
@@ -16,9 +19,8 @@ pub fn compute_step_prob(
             // Calculate block index and prepare cosine buffer
             let i = (block_y * coef.block_w + block_x) as usize;
             // 8x8 block buffer
-            let mut cosbs: [f32; 64] = cos[i * 64..(i + 1) * 64]
-                .try_into()
-                .expect("Invalid cosine transform data length");
+            let mut cosbs = [0.0; 64];
+            S::widen_slice(&cos[i * 64..(i + 1) * 64], &mut cosbs);
 
             // Process each coefficient in current block
             for (j, cosb) in cosbs.iter_mut().enumerate() {
@@ -1,11 +1,11 @@
-use crate::utils::aux::Aux;
+use crate::utils::{aux::Aux, store::Widen};
 
 /// Computes the Total Variation (TV) regularization term and its gradient
-pub fn compute_step_tv(
+pub fn compute_step_tv<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
 ) -> f64 {
     let mut tv = 0.0;
 
@@ -26,11 +26,11 @@ pub fn compute_step_tv(
     tv
 }
 
-pub fn compute_step_tv_inner(
+pub fn compute_step_tv_inner<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     curr_row_idx: u32,
     curr_row: u32,
     tv: &mut f64,
@@ -87,7 +87,7 @@ pub fn compute_step_tv_inner(
 
     // store for use in tv2
     for c in 0..nchannel {
-        auxs[c].pixel_diff.x[curr_px_idx] = g_xs[c];
-        auxs[c].pixel_diff.y[curr_px_idx] = g_ys[c];
+        auxs[c].pixel_diff.x[curr_px_idx] = S::narrow(g_xs[c]);
+        auxs[c].pixel_diff.y[curr_px_idx] = S::narrow(g_ys[c]);
     }
 }
@@ -1,11 +1,11 @@
-use crate::utils::{aux::Aux, macros::mul_add};
+use crate::utils::{aux::Aux, macros::mul_add, store::Widen};
 
 /// Computes the Total Generalized Variation (TGV) regularization term and its gradient
-pub fn compute_step_tv2(
+pub fn compute_step_tv2<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     alpha: f32,
 ) -> f64 {
     let mut tv2 = 0.0;
@@ -29,11 +29,11 @@ pub fn compute_step_tv2(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn compute_step_tv2_inner(
+fn compute_step_tv2_inner<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     alpha: f32,
     curr_x: u32,
     curr_y: u32,
@@ -55,8 +55,8 @@ fn compute_step_tv2_inner(
             let a = (curr_y * max_rounded_px_w + curr_x) as usize;
             let b = (curr_y * max_rounded_px_w + (curr_x - 1)) as usize;
 
-            g_xxs[c] = aux.pixel_diff.x[a] - aux.pixel_diff.x[b];
-            g_yx = aux.pixel_diff.y[a] - aux.pixel_diff.y[b];
+            g_xxs[c] = aux.pixel_diff.x[a].widen() - aux.pixel_diff.x[b].widen();
+            g_yx = aux.pixel_diff.y[a].widen() - aux.pixel_diff.y[b].widen();
         }
 
         // backward difference y
@@ -64,8 +64,8 @@ fn compute_step_tv2_inner(
             let a = (curr_y * max_rounded_px_w + curr_x) as usize;
             let b = ((curr_y - 1) * max_rounded_px_w + curr_x) as usize;
 
-            g_yys[c] = aux.pixel_diff.y[a] - aux.pixel_diff.y[b];
-            g_xy = aux.pixel_diff.x[a] - aux.pixel_diff.x[b];
+            g_yys[c] = aux.pixel_diff.y[a].widen() - aux.pixel_diff.y[b].widen();
+            g_xy = aux.pixel_diff.x[a].widen() - aux.pixel_diff.x[b].widen();
         }
 
         // symmetrize
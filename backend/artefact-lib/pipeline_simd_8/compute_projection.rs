@@ -1,16 +1,17 @@
 use super::{SIMD8Coef, f32x8};
 use crate::utils::{
     aux::Aux,
-    boxing::{boxing, unboxing},
+    boxing::{box_block, unbox_block},
     dct::{dct8x8s, idct8x8s},
+    store::Widen,
     traits::{Clamp, FromSlice, WriteTo},
 };
 use zune_jpeg::sample_factor::SampleFactor;
 
-pub fn compute_projection(
+pub fn compute_projection<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
-    aux: &mut Aux,
+    aux: &mut Aux<S>,
     coef: &SIMD8Coef,
 ) {
     let resample = coef.rounded_px_w != max_rounded_px_w || coef.rounded_px_h != max_rounded_px_h;
@@ -44,7 +45,10 @@ pub fn compute_projection(
                     f32::from(coef.horizontal_samp_factor.u8() * coef.vertical_samp_factor.u8());
 
                 debug_assert!(cx < coef.rounded_px_w && cy < coef.rounded_px_h);
-                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = mean;
+                // keep exactly what gets stored, so that adding it back is lossless
+                let stored = S::narrow(mean);
+                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = stored;
+                let mean = stored.widen();
 
                 match (coef.vertical_samp_factor, coef.horizontal_samp_factor) {
                     (SampleFactor::One, SampleFactor::One) => {
@@ -69,74 +73,55 @@ pub fn compute_projection(
         }
     }
 
-    // Project onto DCT box
-    boxing(
-        if resample {
-            &aux.pixel_diff.y
-        } else {
-            &aux.fdata
-        },
-        aux.pixel_diff.x.as_mut(),
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
+    // Project onto DCT box, one block at a time
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            let i = (block_y * coef.block_w + block_x) as usize;
 
-    for i in 0..coef.block_count as usize {
-        dct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
-    }
+            let mut block = if resample {
+                box_block(&aux.pixel_diff.y, coef.rounded_px_w, block_x, block_y)
+            } else {
+                box_block(&aux.fdata, coef.rounded_px_w, block_x, block_y)
+            };
 
-    // Clamp DCT coefficients
-    for i in 0..coef.block_count as usize {
-        for j in 0..8 {
-            let a = i * 64 + j * 8;
-            let b = a + 7;
-            let old = &mut aux.pixel_diff.x[a..=b];
+            dct8x8s(&mut block);
 
-            let max = coef.dequant_dct_coefs_max[i * 8 + j];
-            let min = coef.dequant_dct_coefs_min[i * 8 + j];
+            // Clamp DCT coefficients
+            for j in 0..8 {
+                let row = &mut block[j * 8..(j + 1) * 8];
 
-            f32x8::from_slc(old).clmp(min, max).write_to(old);
-        }
-    }
+                let max = coef.dequant_dct_coefs_max[i * 8 + j];
+                let min = coef.dequant_dct_coefs_min[i * 8 + j];
 
-    // Save a copy of the DCT values for step_prob
-    aux.cos = aux.pixel_diff.x.clone();
+                f32x8::from_slc(row).clmp(min, max).write_to(row);
+            }
 
-    // add back the difference (orthogonal to our subsampling vector)
-    for i in 0..coef.block_count as usize {
-        idct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
-    }
+            // Save a copy of the DCT values for step_prob
+            S::narrow_slice(&block, &mut aux.cos[i * 64..(i + 1) * 64]);
 
-    unboxing(
-        &aux.pixel_diff.x,
-        if resample {
-            aux.pixel_diff.y.as_mut()
-        } else {
-            aux.fdata.as_mut()
-        },
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
+            // add back the difference (orthogonal to our subsampling vector)
+            idct8x8s(&mut block);
+
+            if resample {
+                unbox_block(
+                    &block,
+                    &mut aux.pixel_diff.y,
+                    coef.rounded_px_w,
+                    block_x,
+                    block_y,
+                );
+            } else {
+                unbox_block(&block, &mut aux.fdata, coef.rounded_px_w, block_x, block_y);
+            }
+        }
+    }
 
     // Add back the difference
     if resample {
         for px_row in 0..coef.rounded_px_h {
             for row_idx in 0..coef.rounded_px_w {
-                let mean = aux.pixel_diff.y[(px_row * coef.rounded_px_w + row_idx) as usize];
+                let mean =
+                    aux.pixel_diff.y[(px_row * coef.rounded_px_w + row_idx) as usize].widen();
                 match (coef.vertical_samp_factor, coef.horizontal_samp_factor) {
                     (SampleFactor::One, SampleFactor::One) => {
                         aux.fdata[(px_row * max_rounded_px_w + row_idx) as usize] += mean;
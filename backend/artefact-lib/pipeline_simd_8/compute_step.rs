@@ -8,50 +8,79 @@ use super::{
 };
 use crate::utils::{
     aux::Aux,
+    guide::compute_step_chroma_guide,
     macros::mul_add,
+    store::Widen,
+    temporal::{Temporal, compute_step_temporal},
+    timing::{Stage, Timings, timed},
     traits::{FromSlice, WriteTo},
 };
 
 #[allow(clippy::too_many_arguments)]
-pub fn compute_step(
+pub fn compute_step<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     max_rounded_px_count: usize,
     nchannel: usize,
     coefs: &[SIMD8Coef],
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     step_size: f32,
     weight: f32,
     pweight: &[f32; 3],
+    chroma_guide: f32,
+    temporal: Option<Temporal>,
+    timings: Option<&Timings>,
 ) {
     auxs.par_iter_mut().enumerate().for_each(|(c, aux)| {
-        aux.obj_gradient = vec![0.0; max_rounded_px_count];
+        aux.obj_gradient.fill(0.0);
 
         // DCT coefficient distance
         if pweight[c] != 0.0 {
-            compute_step_prob(
-                max_rounded_px_w,
-                max_rounded_px_h,
-                pweight[c] * 2.0 * 255.0 * 2.0_f32.sqrt(),
-                &coefs[c],
-                &aux.cos,
-                &mut aux.obj_gradient,
+            timed!(
+                timings,
+                Stage::Prob,
+                compute_step_prob(
+                    max_rounded_px_w,
+                    max_rounded_px_h,
+                    pweight[c] * 2.0 * 255.0 * 2.0_f32.sqrt(),
+                    &coefs[c],
+                    &aux.cos,
+                    &mut aux.obj_gradient,
+                )
             );
         }
     });
 
     // TV computation
-    compute_step_tv(max_rounded_px_w, max_rounded_px_h, nchannel, auxs);
+    timed!(
+        timings,
+        Stage::Tv,
+        compute_step_tv(max_rounded_px_w, max_rounded_px_h, nchannel, auxs)
+    );
 
     // TGV second order
-    compute_step_tv2(
-        max_rounded_px_w,
-        max_rounded_px_h,
-        nchannel,
-        auxs,
-        weight / 2.0_f32.sqrt(),
+    timed!(
+        timings,
+        Stage::Tgv,
+        compute_step_tv2(
+            max_rounded_px_w,
+            max_rounded_px_h,
+            nchannel,
+            auxs,
+            weight / 2.0_f32.sqrt(),
+        )
     );
 
+    // Luma-guided chroma
+    if nchannel == 3 && chroma_guide != 0.0 {
+        compute_step_chroma_guide(max_rounded_px_w, max_rounded_px_h, auxs, chroma_guide);
+    }
+
+    // Temporal smoothness
+    if let Some(temporal) = temporal {
+        compute_step_temporal(auxs, temporal);
+    }
+
     auxs.par_iter_mut().enumerate().for_each(|(c, aux)| {
         // ===== Performs a gradient descent step in the direction of the
         // objective gradient with a specified step size. The gradient is
@@ -78,6 +107,10 @@ pub fn compute_step(
         }
 
         // ===== Project onto DCT basis =====
-        compute_projection(max_rounded_px_w, max_rounded_px_h, aux, &coefs[c]);
+        timed!(
+            timings,
+            Stage::Projection,
+            compute_projection(max_rounded_px_w, max_rounded_px_h, aux, &coefs[c])
+        );
     });
 }
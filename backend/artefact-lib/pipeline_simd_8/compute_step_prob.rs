@@ -1,6 +1,7 @@
 use super::{SIMD8Coef, f32x8};
 use crate::utils::{
     dct::idct8x8s,
+    store::Widen,
     traits::{FromSlice, WriteTo},
 };
 use zune_jpeg::sample_factor::SampleFactor;
@@ -8,12 +9,12 @@ use zune_jpeg::sample_factor::SampleFactor;
 // Compute objective gradient for the distance of DCT coefficients from normal decoding
 // N.B. destroys cos
 #[allow(unused_variables)]
-pub fn compute_step_prob(
+pub fn compute_step_prob<S: Widen>(
     max_rounded_px_w: u32,    // Maximum width after rounding to block size
     max_rounded_px_h: u32,    // Maximum height after rounding to block size
     alpha: f32,               // Learning rate parameter
     coef: &SIMD8Coef,         // JPEG coefficient data
-    cos: &[f32],              // Cosine transform data
+    cos: &[S],                // Cosine transform data
     obj_gradient: &mut [f32], // Output gradient buffer
 ) {
     // Iterate through each 8x8 block in the image
@@ -22,9 +23,8 @@ pub fn compute_step_prob(
             // Calculate block index and prepare cosine buffer
             let i = (block_y * coef.block_w + block_x) as usize;
             // 8x8 block buffer
-            let mut cosbs: [f32; 64] = cos[i * 64..(i + 1) * 64]
-                .try_into()
-                .expect("Invalid cosine transform data length");
+            let mut cosbs = [0.0; 64];
+            S::widen_slice(&cos[i * 64..(i + 1) * 64], &mut cosbs);
 
             // Process each coefficient in current block
             for j in 0..8 {
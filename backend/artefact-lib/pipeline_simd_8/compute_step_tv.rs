@@ -10,15 +10,16 @@ use crate::utils::traits::SafeDiv;
 use super::f32x8;
 use crate::utils::{
     aux::Aux,
+    store::Widen,
     traits::{AddSlice, FromSlice, WriteTo},
 };
 
 #[allow(unused)]
-pub fn compute_step_tv(
+pub fn compute_step_tv<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
 ) {
     for curr_row in 0..max_rounded_px_h {
         for curr_row_px_idx in (0..max_rounded_px_w).step_by(8) {
@@ -34,11 +35,11 @@ pub fn compute_step_tv(
     }
 }
 
-fn compute_step_tv_inner(
+fn compute_step_tv_inner<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     curr_row_px_idx: u32,
     curr_row: u32,
 ) {
@@ -7,15 +7,16 @@ use std::{
 use super::f32x8;
 use crate::utils::{
     aux::Aux,
+    store::Widen,
     traits::{AddSlice, FromSlice, SafeDiv, WriteTo},
 };
 
 #[allow(unused)]
-pub fn compute_step_tv2(
+pub fn compute_step_tv2<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     alpha: f32,
 ) {
     let alpha = f32x8::splat(alpha / (nchannel as f32).sqrt());
@@ -36,11 +37,11 @@ pub fn compute_step_tv2(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn compute_step_tv2_inner(
+fn compute_step_tv2_inner<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     alpha: f32x8,
     curr_row_px_idx: u32,
     curr_row: u32,
@@ -1,16 +1,16 @@
 use std::simd::{cmp::SimdPartialEq, f32x64, StdFloat};
 
-use crate::utils::aux::Aux;
+use crate::utils::{aux::Aux, store::Widen};
 
 /// This unfortunately slower than [`f32x8`].
 ///
 /// [`f32x8`]: wide::f32x8
 #[allow(unused)]
-pub fn compute_step_tv_simd_64(
+pub fn compute_step_tv_simd_64<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
 ) {
     let alpha = 1.0 / (nchannel as f32).sqrt();
 
@@ -29,11 +29,11 @@ pub fn compute_step_tv_simd_64(
     }
 }
 
-fn compute_step_tv_inner(
+fn compute_step_tv_inner<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     curr_row_px_idx: u32,
     curr_row: u32,
     alpha: f32x64,
@@ -6,6 +6,7 @@ use rayon::prelude::*;
 use super::f32x8;
 use crate::utils::{
     aux::Aux,
+        store::Widen,
     traits::{FromSlice, SafeDiv, WriteTo},
 };
 
@@ -14,11 +15,11 @@ use crate::utils::{
 ///
 /// [`compute_step_tv_simd`]: crate::compute::simd::compute_step_tv::compute_step_tv_simd
 #[allow(unused)]
-pub fn compute_step_tv_simd_par(
+pub fn compute_step_tv_simd_par<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
 ) {
     let alpha = f32x8::splat(1.0 / (nchannel as f32).sqrt());
     let max_rounded_px_count = (max_rounded_px_w * max_rounded_px_h) as usize;
@@ -84,10 +85,10 @@ pub fn compute_step_tv_simd_par(
         });
 }
 
-fn compute_forward_differents(
+fn compute_forward_differents<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
-    aux: &Aux,
+    aux: &Aux<S>,
     curr_row_px_idx: u32,
     curr_row: u32,
     chan_g_xs: &mut [f32x8],
@@ -142,12 +143,12 @@ fn compute_forward_differents(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn compute_derivatives(
+fn compute_derivatives<S: Widen>(
     curr_row: u32,
     curr_row_px_idx: u32,
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
-    aux: &mut Aux,
+    aux: &mut Aux<S>,
     curr_px_idx_start_of_group: usize,
     g_xs: f32x8,
     g_ys: f32x8,
@@ -14,34 +14,73 @@ use rayon::prelude::*;
 
 use crate::{
     jpeg::Coefficient,
-    pipeline_simd_8::{coef::SIMD8Coef, compute_step::compute_step},
-    utils::{aux::Aux, macros::mul_add},
+    pipeline_simd_8::{
+        coef::SIMD8Coef, compute_projection::compute_projection, compute_step::compute_step,
+    },
+    utils::{
+        deblock::deblock_components,
+        macros::mul_add,
+        store::Widen,
+        temporal::Temporal,
+        timing::{Stage, Timings, timed},
+    },
 };
 
+/// Working buffers kept across the frames of a [`Sequence`](crate::Sequence)
+pub type Buffers<S> = crate::utils::aux::Buffers<SIMD8Coef, S>;
+
 #[allow(unused)]
-pub fn compute(
+pub fn compute<S: Widen>(
     nchannel: usize,
     coefs: Vec<Coefficient>,
     weight: f32,
     pweight: [f32; 3],
+    chroma_guide: f32,
     iterations: usize,
+    init: Option<Vec<Vec<f32>>>,
+    buffers: Option<&mut Buffers<S>>,
+    temporal: Option<Temporal>,
+    timings: Option<&Timings>,
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     max_rounded_px_count: usize,
 ) -> Vec<Vec<f32>> {
-    let coefs: Vec<SIMD8Coef> = coefs.into_par_iter().map(SIMD8Coef::from).collect();
+    let mut fresh = Buffers::default();
+    let reuse = buffers.is_some();
+    let buffers = buffers.unwrap_or(&mut fresh);
+
+    let mut coefs = timed!(timings, Stage::CoefConversion, buffers.convert(coefs));
 
     // Initialize working buffers for each channel
-    let mut auxs = (0..nchannel)
-        .map(|c| {
-            Aux::init(
-                max_rounded_px_w,
-                max_rounded_px_h,
-                max_rounded_px_count,
-                &coefs[c],
-            )
-        })
-        .collect::<Vec<_>>();
+    let mut auxs = timed!(
+        timings,
+        Stage::AuxInit,
+        buffers.auxs(
+            nchannel,
+            max_rounded_px_w,
+            max_rounded_px_h,
+            max_rounded_px_count,
+            &coefs,
+        )
+    );
+
+    // Start from a given solution instead of the plain decode
+    if let Some(init) = init {
+        for (aux, fdata) in auxs.iter_mut().zip(init) {
+            aux.set_fista(&fdata);
+            aux.fdata = fdata;
+        }
+    }
+
+    // The plain decode is only needed to initialize fdata, its buffer is kept
+    // for the next frame when reusing them
+    for coef in &mut coefs {
+        if reuse {
+            coef.image_data.clear();
+        } else {
+            coef.image_data = Vec::new();
+        }
+    }
 
     // Radius of [-0.5, 0.5]^(h*w)
     let radius = (max_rounded_px_count as f32).sqrt() / 2.0;
@@ -55,9 +94,10 @@ pub fn compute(
 
         auxs.par_iter_mut().for_each(|aux| {
             for i in 0..max_rounded_px_count {
-                aux.fista[i] = mul_add!(factor, aux.fdata[i] - aux.fista[i], aux.fdata[i]);
+                let prev = aux.fista[i].widen();
+                aux.fista[i] = S::narrow(aux.fdata[i]);
+                aux.fdata[i] = mul_add!(factor, aux.fdata[i] - prev, aux.fdata[i]);
             }
-            std::mem::swap(&mut aux.fdata, &mut aux.fista);
         });
 
         term = next_term;
@@ -73,8 +113,29 @@ pub fn compute(
             radius / (1.0 + iterations as f32).sqrt(),
             weight,
             &pweight,
+            chroma_guide,
+            temporal,
+            timings,
         );
     }
 
-    auxs.into_iter().map(|aux| aux.fdata).collect()
+    buffers.keep(coefs, auxs)
+}
+
+/// Single pass of quantization-aware deblocking, followed by a projection back
+/// into the quantization box
+#[allow(unused)]
+pub fn deblock(
+    coefs: Vec<Coefficient>,
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    max_rounded_px_count: usize,
+) -> Vec<Vec<f32>> {
+    deblock_components::<SIMD8Coef>(
+        coefs,
+        compute_projection,
+        max_rounded_px_w,
+        max_rounded_px_h,
+        max_rounded_px_count,
+    )
 }
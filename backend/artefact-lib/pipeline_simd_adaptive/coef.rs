@@ -5,7 +5,7 @@ use zune_jpeg::sample_factor::SampleFactor;
 use crate::{
     jpeg::Coefficient,
     utils::{
-        aux::AuxTraits,
+        aux::{AuxTraits, Refill},
         boxing::unboxing,
         dct::idct8x8s,
         traits::{FromSlice, WriteTo},
@@ -36,15 +36,63 @@ pub struct SIMDAdaptiveCoef {
 
 impl From<Coefficient> for SIMDAdaptiveCoef {
     fn from(c: Coefficient) -> Self {
-        let dct_coefs = c
-            .dct_coefs
-            .chunks_exact(64)
-            .map(f32x64::from_slc)
-            .collect::<Vec<f32x64>>();
+        let mut coef = Self::default();
+        coef.refill(c);
+        coef
+    }
+}
+
+impl Refill for SIMDAdaptiveCoef {
+    fn refill(&mut self, c: Coefficient) {
+        let mut dct_coefs = std::mem::take(&mut self.dct_coefs);
+        dct_coefs.clear();
+        dct_coefs.extend(c.dct_coefs.chunks_exact(64).map(f32x64::from_slc));
 
         let quant_table = f32x64::from_array(c.quant_table);
 
-        Self {
+        let mut dequant_dct_coefs_min = std::mem::take(&mut self.dequant_dct_coefs_min);
+        dequant_dct_coefs_min.clear();
+        dequant_dct_coefs_min.extend(
+            dct_coefs
+                .iter()
+                .map(|dct_coefs| (*dct_coefs - f32x64::splat(0.5)) * quant_table),
+        );
+
+        let mut dequant_dct_coefs_max = std::mem::take(&mut self.dequant_dct_coefs_max);
+        dequant_dct_coefs_max.clear();
+        dequant_dct_coefs_max.extend(
+            dct_coefs
+                .iter()
+                .map(|dct_coefs| (*dct_coefs + f32x64::splat(0.5)) * quant_table),
+        );
+
+        let mut image_data = std::mem::take(&mut self.image_data);
+        image_data.clear();
+        image_data.resize(c.rounded_px_count as usize, 0.0);
+
+        for i in 0..(c.block_count as usize) {
+            let result = dct_coefs[i] * quant_table;
+            result.write_to(&mut image_data[i * 64..(i + 1) * 64]);
+
+            idct8x8s(
+                image_data[i * 64..(i + 1) * 64]
+                    .as_mut()
+                    .try_into()
+                    .expect("Invalid image_data length"),
+            );
+        }
+
+        // 8x8 -> 64x1
+        unboxing(
+            &image_data.clone(),
+            image_data.as_mut(),
+            c.rounded_px_w,
+            c.rounded_px_h,
+            c.block_w,
+            c.block_h,
+        );
+
+        *self = Self {
             rounded_px_w: c.rounded_px_w,
             rounded_px_h: c.rounded_px_h,
             rounded_px_count: c.rounded_px_count,
@@ -56,47 +104,12 @@ impl From<Coefficient> for SIMDAdaptiveCoef {
 
             quant_table_squared: quant_table * quant_table,
 
-            dequant_dct_coefs_min: dct_coefs
-                .iter()
-                .map(|dct_coefs| (*dct_coefs - f32x64::splat(0.5)) * quant_table)
-                .collect(),
-
-            dequant_dct_coefs_max: dct_coefs
-                .iter()
-                .map(|dct_coefs| (*dct_coefs + f32x64::splat(0.5)) * quant_table)
-                .collect(),
-
-            image_data: {
-                let mut tmp = vec![0.0; c.rounded_px_count as usize];
-
-                for i in 0..(c.block_count as usize) {
-                    let result = dct_coefs[i] * quant_table;
-                    result.write_to(&mut tmp[i * 64..(i + 1) * 64]);
-
-                    idct8x8s(
-                        tmp[i * 64..(i + 1) * 64]
-                            .as_mut()
-                            .try_into()
-                            .expect("Invalid image_data length"),
-                    );
-                }
-
-                // 8x8 -> 64x1
-                unboxing(
-                    &tmp.clone(),
-                    tmp.as_mut(),
-                    c.rounded_px_w,
-                    c.rounded_px_h,
-                    c.block_w,
-                    c.block_h,
-                );
-
-                tmp
-            },
-
+            dequant_dct_coefs_min,
+            dequant_dct_coefs_max,
+            image_data,
             dct_coefs,
             quant_table,
-        }
+        };
     }
 }
 
@@ -5,15 +5,15 @@ use zune_jpeg::sample_factor::SampleFactor;
 use super::coef::SIMDAdaptiveCoef;
 use crate::utils::{
     aux::Aux,
-    boxing::{boxing, unboxing},
+    boxing::{box_block, unbox_block},
     dct::{dct8x8s, idct8x8s},
-    traits::WriteTo,
+    store::Widen,
 };
 
-pub fn compute_projection(
+pub fn compute_projection<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
-    aux: &mut Aux,
+    aux: &mut Aux<S>,
     coef: &SIMDAdaptiveCoef,
 ) {
     let resample = coef.rounded_px_w != max_rounded_px_w || coef.rounded_px_h != max_rounded_px_h;
@@ -47,7 +47,10 @@ pub fn compute_projection(
                     f32::from(coef.horizontal_samp_factor.u8() * coef.vertical_samp_factor.u8());
 
                 debug_assert!(cx < coef.rounded_px_w && cy < coef.rounded_px_h);
-                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = mean;
+                // keep exactly what gets stored, so that adding it back is lossless
+                let stored = S::narrow(mean);
+                aux.pixel_diff.y[(cy * coef.rounded_px_w + cx) as usize] = stored;
+                let mean = stored.widen();
 
                 match (coef.vertical_samp_factor, coef.horizontal_samp_factor) {
                     (SampleFactor::One, SampleFactor::One) => {
@@ -72,72 +75,51 @@ pub fn compute_projection(
         }
     }
 
-    // Project onto DCT box
-    boxing(
-        if resample {
-            &aux.pixel_diff.y
-        } else {
-            &aux.fdata
-        },
-        aux.pixel_diff.x.as_mut(),
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
-
-    for i in 0..coef.block_count as usize {
-        dct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
-    }
-
-    // Clamp DCT coefficients
-    for i in 0..coef.block_count as usize {
-        let a = i * 64;
-        let b = a + 63;
-        let old = &mut aux.pixel_diff.x[a..=b];
-
-        let max = coef.dequant_dct_coefs_max[i];
-        let min = coef.dequant_dct_coefs_min[i];
-
-        f32x64::from_slice(old).simd_clamp(min, max).write_to(old);
-    }
-
-    // Save a copy of the DCT values for step_prob
-    aux.cos = aux.pixel_diff.x.clone();
-
-    // add back the difference (orthogonal to our subsampling vector)
-    for i in 0..coef.block_count as usize {
-        idct8x8s(
-            aux.pixel_diff.x[i * 64..(i + 1) * 64]
-                .as_mut()
-                .try_into()
-                .expect("Invalid pixel difference data length"),
-        );
+    // Project onto DCT box, one block at a time
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            let i = (block_y * coef.block_w + block_x) as usize;
+
+            let mut block = if resample {
+                box_block(&aux.pixel_diff.y, coef.rounded_px_w, block_x, block_y)
+            } else {
+                box_block(&aux.fdata, coef.rounded_px_w, block_x, block_y)
+            };
+
+            dct8x8s(&mut block);
+
+            // Clamp DCT coefficients
+            let max = coef.dequant_dct_coefs_max[i];
+            let min = coef.dequant_dct_coefs_min[i];
+
+            block = f32x64::from_array(block).simd_clamp(min, max).to_array();
+
+            // Save a copy of the DCT values for step_prob
+            S::narrow_slice(&block, &mut aux.cos[i * 64..(i + 1) * 64]);
+
+            // add back the difference (orthogonal to our subsampling vector)
+            idct8x8s(&mut block);
+
+            if resample {
+                unbox_block(
+                    &block,
+                    &mut aux.pixel_diff.y,
+                    coef.rounded_px_w,
+                    block_x,
+                    block_y,
+                );
+            } else {
+                unbox_block(&block, &mut aux.fdata, coef.rounded_px_w, block_x, block_y);
+            }
+        }
     }
 
-    unboxing(
-        &aux.pixel_diff.x,
-        if resample {
-            aux.pixel_diff.y.as_mut()
-        } else {
-            aux.fdata.as_mut()
-        },
-        coef.rounded_px_w,
-        coef.rounded_px_h,
-        coef.block_w,
-        coef.block_h,
-    );
-
     // Add back the difference
     if resample {
         for px_row in 0..coef.rounded_px_h {
             for row_idx in 0..coef.rounded_px_w {
-                let mean = aux.pixel_diff.y[(px_row * coef.rounded_px_w + row_idx) as usize];
+                let mean =
+                    aux.pixel_diff.y[(px_row * coef.rounded_px_w + row_idx) as usize].widen();
                 match (coef.vertical_samp_factor, coef.horizontal_samp_factor) {
                     (SampleFactor::One, SampleFactor::One) => {
                         aux.fdata[(px_row * max_rounded_px_w + row_idx) as usize] += mean;
@@ -11,59 +11,88 @@ use crate::{
     pipeline_simd_8::f32x8,
     utils::{
         aux::Aux,
+        guide::compute_step_chroma_guide,
         macros::mul_add,
+        store::Widen,
+        temporal::{Temporal, compute_step_temporal},
+        timing::{Stage, Timings, timed},
         traits::{FromSlice, WriteTo},
     },
 };
 
 #[allow(clippy::too_many_arguments)]
-pub fn compute_step(
+pub fn compute_step<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     max_rounded_px_count: usize,
     nchannel: usize,
     coefs: &[SIMDAdaptiveCoef],
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     step_size: f32,
     weight: f32,
     pweight: &[f32; 3],
+    chroma_guide: f32,
+    temporal: Option<Temporal>,
+    timings: Option<&Timings>,
     adaptive_widths: &[AdaptiveWidth],
 ) {
     auxs.par_iter_mut().enumerate().for_each(|(c, aux)| {
-        aux.obj_gradient = vec![0.0; max_rounded_px_count];
+        aux.obj_gradient.fill(0.0);
 
         // DCT coefficient distance
         if pweight[c] != 0.0 {
-            compute_step_prob(
-                max_rounded_px_w,
-                max_rounded_px_h,
-                pweight[c] * 2.0 * 255.0 * 2.0_f32.sqrt(),
-                &coefs[c],
-                &aux.cos,
-                &mut aux.obj_gradient,
+            timed!(
+                timings,
+                Stage::Prob,
+                compute_step_prob(
+                    max_rounded_px_w,
+                    max_rounded_px_h,
+                    pweight[c] * 2.0 * 255.0 * 2.0_f32.sqrt(),
+                    &coefs[c],
+                    &aux.cos,
+                    &mut aux.obj_gradient,
+                )
             );
         }
     });
 
     // TV computation
-    compute_step_tv(
-        max_rounded_px_w,
-        max_rounded_px_h,
-        nchannel,
-        auxs,
-        adaptive_widths,
+    timed!(
+        timings,
+        Stage::Tv,
+        compute_step_tv(
+            max_rounded_px_w,
+            max_rounded_px_h,
+            nchannel,
+            auxs,
+            adaptive_widths,
+        )
     );
 
     // TGV second order
-    compute_step_tv2(
-        max_rounded_px_w,
-        max_rounded_px_h,
-        nchannel,
-        auxs,
-        weight / 2.0_f32.sqrt(),
-        adaptive_widths,
+    timed!(
+        timings,
+        Stage::Tgv,
+        compute_step_tv2(
+            max_rounded_px_w,
+            max_rounded_px_h,
+            nchannel,
+            auxs,
+            weight / 2.0_f32.sqrt(),
+            adaptive_widths,
+        )
     );
 
+    // Luma-guided chroma
+    if nchannel == 3 && chroma_guide != 0.0 {
+        compute_step_chroma_guide(max_rounded_px_w, max_rounded_px_h, auxs, chroma_guide);
+    }
+
+    // Temporal smoothness
+    if let Some(temporal) = temporal {
+        compute_step_temporal(auxs, temporal);
+    }
+
     auxs.par_iter_mut().enumerate().for_each(|(c, aux)| {
         // ===== Performs a gradient descent step in the direction of the
         // objective gradient with a specified step size. The gradient is
@@ -90,6 +119,10 @@ pub fn compute_step(
         }
 
         // ===== Project onto DCT basis =====
-        compute_projection(max_rounded_px_w, max_rounded_px_h, aux, &coefs[c]);
+        timed!(
+            timings,
+            Stage::Projection,
+            compute_projection(max_rounded_px_w, max_rounded_px_h, aux, &coefs[c])
+        );
     });
 }
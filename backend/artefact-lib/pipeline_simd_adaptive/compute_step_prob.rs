@@ -6,17 +6,21 @@ use std::{
 use zune_jpeg::sample_factor::SampleFactor;
 
 use super::coef::SIMDAdaptiveCoef;
-use crate::utils::{dct::idct8x8s, traits::WriteTo};
+use crate::utils::{
+    dct::idct8x8s,
+    store::Widen,
+    traits::{FromSlice, WriteTo},
+};
 
 // Compute objective gradient for the distance of DCT coefficients from normal decoding
 // N.B. destroys cos
 #[allow(unused_variables)]
-pub fn compute_step_prob(
+pub fn compute_step_prob<S: Widen>(
     max_rounded_px_w: u32,    // Maximum width after rounding to block size
     max_rounded_px_h: u32,    // Maximum height after rounding to block size
     alpha: f32,               // Learning rate parameter
     coef: &SIMDAdaptiveCoef,  // JPEG coefficient data
-    cos: &[f32],              // Cosine transform data
+    cos: &[S],                // Cosine transform data
     obj_gradient: &mut [f32], // Output gradient buffer
 ) {
     // Iterate through each 8x8 block in the image
@@ -28,7 +32,7 @@ pub fn compute_step_prob(
             // 8x8 block buffer
             let mut cosbs = [0.0; 64];
 
-            f32x64::from_slice(&cos[i * 64..(i + 1) * 64])
+            f32x64::from_slc(&cos[i * 64..(i + 1) * 64])
                 .sub(coef.dct_coefs[i] * coef.quant_table)
                 .div(coef.quant_table_squared)
                 .write_to(&mut cosbs);
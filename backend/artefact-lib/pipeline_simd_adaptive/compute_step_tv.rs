@@ -8,14 +8,15 @@ use paste::paste;
 use super::adaptive_width::AdaptiveWidth;
 use crate::utils::{
     aux::Aux,
+    store::Widen,
     traits::{AddSlice, FromSlice, WriteTo},
 };
 
-pub fn compute_step_tv(
+pub fn compute_step_tv<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     adaptive_widths: &[AdaptiveWidth],
 ) {
     for curr_row in 0..max_rounded_px_h {
@@ -48,11 +49,11 @@ pub fn compute_step_tv(
 macro_rules! gen_func {
     (width: $width:literal, pad: $pad:tt) => {
         paste! {
-            fn [<compute_step_tv_inner_ $width>](
+            fn [<compute_step_tv_inner_ $width>]<S: Widen>(
                 max_rounded_px_w: u32,
                 max_rounded_px_h: u32,
                 nchannel: usize,
-                auxs: &mut [Aux],
+                auxs: &mut [Aux<S>],
                 curr_row_px_idx: u32,
                 curr_row: u32,
             ) {
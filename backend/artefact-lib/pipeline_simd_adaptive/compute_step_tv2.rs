@@ -8,14 +8,15 @@ use paste::paste;
 use super::adaptive_width::AdaptiveWidth;
 use crate::utils::{
     aux::Aux,
+    store::Widen,
     traits::{AddSlice, FromSlice, SafeDiv, WriteTo},
 };
 
-pub fn compute_step_tv2(
+pub fn compute_step_tv2<S: Widen>(
     max_rounded_px_w: u32,
     max_rounded_px_h: u32,
     nchannel: usize,
-    auxs: &mut [Aux],
+    auxs: &mut [Aux<S>],
     alpha: f32,
     adaptive_widths: &[AdaptiveWidth],
 ) {
@@ -53,11 +54,11 @@ macro_rules! gen_func {
     (width: $width:literal, pad: $pad:tt) => {
         paste! {
             #[allow(clippy::too_many_arguments)]
-            fn [<compute_step_tv2_inner_ $width>](
+            fn [<compute_step_tv2_inner_ $width>]<S: Widen>(
                 max_rounded_px_w: u32,
                 max_rounded_px_h: u32,
                 nchannel: usize,
-                auxs: &mut [Aux],
+                auxs: &mut [Aux<S>],
                 alpha: f32,
                 curr_row_px_idx: u32,
                 curr_row: u32,
@@ -0,0 +1,131 @@
+use crate::utils::npy::to_npy;
+
+/// Size of the planes returned by [`crate::Artefact::process_planes`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaneResolution {
+    /// Every plane at the image size, as solved
+    #[default]
+    Full,
+    /// Every plane at the sampling of its JPEG component, e.g. half width and
+    /// height chroma for 4:2:0, obtained by averaging like the encoder does
+    Native,
+}
+
+/// A reconstructed YCbCr component, in row-major order
+///
+/// Values are as solved: luma is centered around 0 (add 128 for the usual
+/// 0-255 range), chroma too, and nothing is clipped.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>,
+}
+
+impl Plane {
+    /// Crop a solver output to the image size, downsampled by `samp`
+    ///
+    /// # Arguments
+    ///
+    /// * `fdata` - Solver output, `stride` pixels per row
+    /// * `stride` - Maximum rounded pixel width of the image
+    /// * `real_px_w`, `real_px_h` - Size of the image
+    /// * `samp` - Horizontal and vertical downsampling factors
+    pub(crate) fn from_fdata(
+        fdata: &[f32],
+        stride: u32,
+        real_px_w: u32,
+        real_px_h: u32,
+        (h_samp, v_samp): (u32, u32),
+    ) -> Self {
+        let width = real_px_w.div_ceil(h_samp);
+        let height = real_px_h.div_ceil(v_samp);
+
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for cy in 0..height {
+            for cx in 0..width {
+                let mut sum = 0.0;
+                let mut count = 0.0;
+                for y in cy * v_samp..((cy + 1) * v_samp).min(real_px_h) {
+                    for x in cx * h_samp..((cx + 1) * h_samp).min(real_px_w) {
+                        sum += fdata[(y * stride + x) as usize];
+                        count += 1.0;
+                    }
+                }
+                data.push(sum / count);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Serialize planes of the same size to a `.npy` file, as a `float32` array of
+/// shape `(components, height, width)`
+///
+/// # Errors
+/// Returns an error if the planes are not all of the same size, use
+/// [`PlaneResolution::Full`] for subsampled JPEGs.
+pub fn planes_to_npy(planes: &[Plane]) -> Result<Vec<u8>, String> {
+    let Some(first) = planes.first() else {
+        return Err("No plane to write".to_string());
+    };
+    if planes
+        .iter()
+        .any(|plane| (plane.width, plane.height) != (first.width, first.height))
+    {
+        return Err("Planes of different sizes cannot be stacked in a .npy array".to_string());
+    }
+
+    Ok(to_npy(
+        &[planes.len(), first.height as usize, first.width as usize],
+        &planes
+            .iter()
+            .flat_map(|plane| plane.data.iter().copied())
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::npy::from_npy;
+
+    #[test]
+    #[allow(clippy::float_cmp)] // averages of small integers, exact in f32
+    fn crops_and_downsamples() {
+        // 3x3 image in a solver output of stride 4
+        let fdata = (0..16).map(|v| v as f32).collect::<Vec<_>>();
+
+        let full = Plane::from_fdata(&fdata, 4, 3, 3, (1, 1));
+        assert_eq!((full.width, full.height), (3, 3));
+        assert_eq!(full.data, [0.0, 1.0, 2.0, 4.0, 5.0, 6.0, 8.0, 9.0, 10.0]);
+
+        // Edge samples only average the pixels inside the image
+        let half = Plane::from_fdata(&fdata, 4, 3, 3, (2, 2));
+        assert_eq!((half.width, half.height), (2, 2));
+        assert_eq!(half.data, [2.5, 4.0, 8.5, 10.0]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn writes_npy() {
+        let plane = |value| Plane {
+            width: 3,
+            height: 2,
+            data: vec![value; 6],
+        };
+        let npy = planes_to_npy(&[plane(1.0), plane(-2.0)]).expect("same sizes");
+        let array = from_npy(&npy).expect("valid .npy");
+        assert_eq!(array.shape, [2, 2, 3]);
+        assert_eq!(array.data[5..7], [1.0, -2.0]);
+
+        let small = Plane::from_fdata(&[0.0; 4], 2, 2, 2, (2, 2));
+        assert!(planes_to_npy(&[plane(0.0), small]).is_err());
+        assert!(planes_to_npy(&[]).is_err());
+    }
+}
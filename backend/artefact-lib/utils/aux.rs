@@ -1,25 +1,33 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    jpeg::Coefficient,
+    utils::store::{Widen, narrow},
+};
+
 #[derive(Debug)]
-pub struct PixelDifference {
-    pub x: Vec<f32>,
-    pub y: Vec<f32>,
+pub struct PixelDifference<S> {
+    pub x: Vec<S>,
+    pub y: Vec<S>,
 }
 
-/// Working buffers for each component
+/// Working buffers for each component, the ones only read back by the compute
+/// kernels stored as `S`, see [`Widen`]
 #[derive(Debug)]
-pub struct Aux {
+pub struct Aux<S = f32> {
     /// DCT coefficients for `step_prob`
-    pub cos: Vec<f32>,
+    pub cos: Vec<S>,
 
     /// Gradient (derivative) of the objective function
     pub obj_gradient: Vec<f32>,
 
-    pub pixel_diff: PixelDifference,
+    pub pixel_diff: PixelDifference<S>,
 
     /// Image data
     pub fdata: Vec<f32>,
 
     /// Previous step image data for FISTA
-    pub fista: Vec<f32>,
+    pub fista: Vec<S>,
 }
 
 pub trait AuxTraits {
@@ -33,7 +41,13 @@ pub trait AuxTraits {
     fn get_cos(&self) -> Vec<f32>;
 }
 
-impl Aux {
+/// Conversion of the decoded coefficients of a component to the layout of a
+/// pipeline, into the buffers of a previous conversion
+pub trait Refill: From<Coefficient> {
+    fn refill(&mut self, coef: Coefficient);
+}
+
+impl<S: Widen> Aux<S> {
     /// Init a new auxilary buffer
     ///
     /// # Arguments
@@ -51,16 +65,181 @@ impl Aux {
         let fdata = coef.get_fdata(max_rounded_px_w, max_rounded_px_h, max_rounded_px_count);
 
         Self {
-            cos: coef.get_cos(),
+            cos: narrow(&coef.get_cos()),
             obj_gradient: vec![0.0; max_rounded_px_count],
 
             pixel_diff: PixelDifference {
-                x: vec![0.0; max_rounded_px_count],
-                y: vec![0.0; max_rounded_px_count],
+                x: vec![S::narrow(0.0); max_rounded_px_count],
+                y: vec![S::narrow(0.0); max_rounded_px_count],
             },
 
-            fista: fdata.clone(),
+            fista: narrow(&fdata),
             fdata,
         }
     }
+
+    /// Like [`Self::init`], into the buffers of a previous solve. `fdata` is
+    /// always new, it is handed out as the output.
+    pub fn refill(
+        &mut self,
+        max_rounded_px_w: u32,
+        max_rounded_px_h: u32,
+        max_rounded_px_count: usize,
+        coef: &impl AuxTraits,
+    ) {
+        self.fdata = coef.get_fdata(max_rounded_px_w, max_rounded_px_h, max_rounded_px_count);
+
+        self.cos.clear();
+        self.cos.extend(coef.get_cos().into_iter().map(S::narrow));
+
+        self.obj_gradient.clear();
+        self.obj_gradient.resize(max_rounded_px_count, 0.0);
+
+        for diff in [&mut self.pixel_diff.x, &mut self.pixel_diff.y] {
+            diff.clear();
+            diff.resize(max_rounded_px_count, S::narrow(0.0));
+        }
+
+        self.fista.clear();
+        self.fista.extend(self.fdata.iter().copied().map(S::narrow));
+    }
+
+    /// Restart FISTA from `fdata`
+    pub fn set_fista(&mut self, fdata: &[f32]) {
+        self.fista.clear();
+        self.fista.extend(fdata.iter().copied().map(S::narrow));
+    }
+}
+
+/// Converted coefficients and working buffers of a solve, kept by a
+/// [`Sequence`](crate::Sequence) for its next frame
+///
+/// A frame of the same geometry reuses all of them, only `fdata` is allocated
+/// again as it is handed out as the output. The buffers grow when needed.
+#[derive(Debug)]
+pub struct Buffers<C, S> {
+    coefs: Vec<C>,
+    auxs: Vec<Aux<S>>,
+}
+
+impl<C, S> Default for Buffers<C, S> {
+    fn default() -> Self {
+        Self {
+            coefs: Vec::new(),
+            auxs: Vec::new(),
+        }
+    }
+}
+
+impl<C: Refill + AuxTraits + Send, S: Widen> Buffers<C, S> {
+    /// Convert `coefs` to the layout of the pipeline, into the buffers of the
+    /// previous solve if any
+    pub fn convert(&mut self, coefs: Vec<Coefficient>) -> Vec<C> {
+        let mut previous = std::mem::take(&mut self.coefs).into_iter();
+        coefs
+            .into_iter()
+            .map(|coef| (coef, previous.next()))
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(coef, previous)| match previous {
+                Some(mut previous) => {
+                    previous.refill(coef);
+                    previous
+                }
+                None => C::from(coef),
+            })
+            .collect()
+    }
+
+    /// Working buffers for the first `nchannel` of `coefs`, see [`Aux::init`],
+    /// reusing the ones of the previous solve if any
+    pub fn auxs(
+        &mut self,
+        nchannel: usize,
+        max_rounded_px_w: u32,
+        max_rounded_px_h: u32,
+        max_rounded_px_count: usize,
+        coefs: &[C],
+    ) -> Vec<Aux<S>> {
+        let mut previous = std::mem::take(&mut self.auxs).into_iter();
+        coefs[..nchannel]
+            .iter()
+            .map(|coef| {
+                previous.next().map_or_else(
+                    || {
+                        Aux::init(
+                            max_rounded_px_w,
+                            max_rounded_px_h,
+                            max_rounded_px_count,
+                            coef,
+                        )
+                    },
+                    |mut aux| {
+                        aux.refill(
+                            max_rounded_px_w,
+                            max_rounded_px_h,
+                            max_rounded_px_count,
+                            coef,
+                        );
+                        aux
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Hand the outputs of a solve out, keeping the other buffers for the next
+    /// one
+    pub fn keep(&mut self, coefs: Vec<C>, mut auxs: Vec<Aux<S>>) -> Vec<Vec<f32>> {
+        let output = auxs
+            .iter_mut()
+            .map(|aux| std::mem::take(&mut aux.fdata))
+            .collect();
+        self.coefs = coefs;
+        self.auxs = auxs;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    use crate::{
+        pipeline_scalar::{Buffers, compute},
+        utils::fixtures::component,
+    };
+
+    fn solve(level: f32, buffers: Option<&mut Buffers<f32>>) -> Vec<Vec<f32>> {
+        let coef = component(4, 2, SampleFactor::One, [8.0; 64], |x, y| {
+            ((x * 3 + y * 5) % 17) as f32 * level
+        });
+        compute::<f32>(
+            1,
+            vec![coef],
+            0.3,
+            [0.001; 3],
+            0.0,
+            20,
+            None,
+            buffers,
+            None,
+            None,
+            32,
+            16,
+            32 * 16,
+        )
+    }
+
+    #[test]
+    fn reuses_buffers() {
+        let mut buffers = Buffers::default();
+        solve(1.0, Some(&mut buffers));
+        let cos = buffers.auxs[0].cos.as_ptr();
+        let image_data = buffers.coefs[0].image_data.as_ptr();
+
+        assert_eq!(solve(2.0, Some(&mut buffers)), solve(2.0, None));
+        assert_eq!(buffers.auxs[0].cos.as_ptr(), cos);
+        assert_eq!(buffers.coefs[0].image_data.as_ptr(), image_data);
+    }
 }
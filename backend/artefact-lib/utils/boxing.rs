@@ -1,3 +1,5 @@
+use crate::utils::store::Widen;
+
 #[cfg(feature = "simd")]
 use crate::{
     pipeline_simd_8::f32x8,
@@ -44,41 +46,43 @@ pub fn unboxing(
     }
 }
 
-/// Convert from 64x1 block to 8x8 block
-pub fn boxing(
-    input: &[f32],
-    output: &mut [f32],
+/// Gather the 8x8 block at (`block_x`, `block_y`) into a 64x1 block
+pub fn box_block<E: Widen>(
+    input: &[E],
     rounded_px_w: u32,
-    rounded_px_h: u32,
-    block_w: u32,
-    block_h: u32,
-) {
-    debug_assert_eq!(rounded_px_w % 8, 0);
-    debug_assert_eq!(rounded_px_h % 8, 0);
-    debug_assert_eq!(input.len(), output.len());
+    block_x: u32,
+    block_y: u32,
+) -> [f32; 64] {
+    let mut block = [0.0; 64];
 
-    let mut index = 0;
+    for in_y in 0..8 {
+        let row_start = ((block_y * 8 + in_y) * rounded_px_w + (block_x * 8)) as usize;
+        let index = in_y as usize * 8;
 
-    for block_y in 0..block_h {
-        for block_x in 0..block_w {
-            #[cfg(feature = "simd")]
-            for in_y in 0..8 {
-                let row_start = ((block_y * 8 + in_y) * rounded_px_w + (block_x * 8)) as usize;
+        E::widen_slice(
+            &input[row_start..row_start + 8],
+            &mut block[index..index + 8],
+        );
+    }
 
-                f32x8::from_slc(&input[row_start..row_start + 8])
-                    .write_to(&mut output[index..index + 8]);
+    block
+}
 
-                index += 8;
-            }
+/// Scatter a 64x1 block back to the 8x8 block at (`block_x`, `block_y`)
+pub fn unbox_block<E: Widen>(
+    block: &[f32; 64],
+    output: &mut [E],
+    rounded_px_w: u32,
+    block_x: u32,
+    block_y: u32,
+) {
+    for in_y in 0..8 {
+        let row_start = ((block_y * 8 + in_y) * rounded_px_w + (block_x * 8)) as usize;
+        let index = in_y as usize * 8;
 
-            #[cfg(not(feature = "simd"))]
-            for in_y in 0..8 {
-                for in_x in 0..8 {
-                    output[index] = input
-                        [((block_y * 8 + in_y) * rounded_px_w + (block_x * 8 + in_x)) as usize];
-                    index += 1;
-                }
-            }
-        }
+        E::narrow_slice(
+            &block[index..index + 8],
+            &mut output[row_start..row_start + 8],
+        );
     }
 }
@@ -0,0 +1,191 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use zune_jpeg::sample_factor::SampleFactor;
+
+use crate::{
+    jpeg::Coefficient,
+    utils::{
+        aux::{Aux, AuxTraits},
+        macros::mul_add,
+    },
+};
+
+/// Single pass of quantization-aware deblocking of every component, followed
+/// by a projection back into the quantization box
+///
+/// Shared by the pipelines, which only differ in their coefficient type.
+///
+/// # Arguments
+///
+/// * `coefs` - The coefficients of the components
+/// * `project` - The projection of the pipeline
+/// * `max_rounded_px_w` - Maximum rounded pixel width of the image
+/// * `max_rounded_px_h` - Maximum rounded pixel height of the image
+/// * `max_rounded_px_count` - 2 above values multiplied
+pub fn deblock_components<C>(
+    coefs: Vec<Coefficient>,
+    project: impl Fn(u32, u32, &mut Aux, &C) + Sync,
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    max_rounded_px_count: usize,
+) -> Vec<Vec<f32>>
+where
+    C: From<Coefficient> + AuxTraits,
+{
+    coefs
+        .into_par_iter()
+        .map(|coef| {
+            let threshold = deblock_threshold(&coef.quant_table);
+            let (horizontal_samp_factor, vertical_samp_factor) =
+                (coef.horizontal_samp_factor, coef.vertical_samp_factor);
+            let coef = C::from(coef);
+
+            let mut aux = Aux::init(
+                max_rounded_px_w,
+                max_rounded_px_h,
+                max_rounded_px_count,
+                &coef,
+            );
+
+            deblock(
+                max_rounded_px_w,
+                max_rounded_px_h,
+                &mut aux.fdata,
+                horizontal_samp_factor,
+                vertical_samp_factor,
+                threshold,
+            );
+
+            project(max_rounded_px_w, max_rounded_px_h, &mut aux, &coef);
+
+            aux.fdata
+        })
+        .collect()
+}
+
+/// Largest step across a block boundary that can still be explained by
+/// quantization of the lowest frequencies
+///
+/// Rounding a coefficient moves it by at most half a quantization step, the DC
+/// and first horizontal/vertical AC coefficients are the ones responsible for
+/// most of the visible blocking.
+pub fn deblock_threshold(quant_table: &[f32; 64]) -> f32 {
+    (quant_table[0] + quant_table[1] + quant_table[8]) / 8.0
+}
+
+/// One pass of a spatial filter across the 8x8 block boundaries of a component
+///
+/// Steps larger than `threshold` are kept as real edges, the rest are spread
+/// over the 2 pixels on each side of the boundary.
+///
+/// # Arguments
+///
+/// * `max_rounded_px_w` - Maximum rounded pixel width of the image
+/// * `max_rounded_px_h` - Maximum rounded pixel height of the image
+/// * `fdata` - Image data of the component, upsampled to the maximum size
+/// * `horizontal_samp_factor` - Horizontal sampling factor of the component
+/// * `vertical_samp_factor` - Vertical sampling factor of the component
+/// * `threshold` - See [`deblock_threshold`]
+pub fn deblock(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    fdata: &mut [f32],
+    horizontal_samp_factor: SampleFactor,
+    vertical_samp_factor: SampleFactor,
+    threshold: f32,
+) {
+    let w = max_rounded_px_w as usize;
+    let h = max_rounded_px_h as usize;
+    let block_px_w = 8 * horizontal_samp_factor.usize();
+    let block_px_h = 8 * vertical_samp_factor.usize();
+
+    // vertical boundaries
+    for y in 0..h {
+        for x in (block_px_w..w).step_by(block_px_w) {
+            let row = y * w;
+            filter_boundary(
+                fdata,
+                [row + x - 2, row + x - 1, row + x, row + x + 1],
+                threshold,
+            );
+        }
+    }
+
+    // horizontal boundaries
+    for y in (block_px_h..h).step_by(block_px_h) {
+        for x in 0..w {
+            filter_boundary(
+                fdata,
+                [(y - 2) * w + x, (y - 1) * w + x, y * w + x, (y + 1) * w + x],
+                threshold,
+            );
+        }
+    }
+}
+
+/// Filter the 4 pixels `[p1, p0 | q0, q1]` straddling a boundary
+fn filter_boundary(fdata: &mut [f32], [p1, p0, q0, q1]: [usize; 4], threshold: f32) {
+    let step = fdata[q0] - fdata[p0];
+
+    // a real edge, or the sides are too busy to tell
+    if step.abs() >= threshold
+        || (fdata[p0] - fdata[p1]).abs() >= threshold / 2.0
+        || (fdata[q1] - fdata[q0]).abs() >= threshold / 2.0
+    {
+        return;
+    }
+
+    let delta = mul_add!(4.0_f32, step, fdata[p1] - fdata[q1]) / 8.0;
+
+    fdata[p1] += delta / 2.0;
+    fdata[p0] += delta;
+    fdata[q0] -= delta;
+    fdata[q1] -= delta / 2.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    use crate::{
+        jpeg::Coefficient,
+        utils::fixtures::{assert_in_box, component},
+    };
+
+    /// Flat 8x8 blocks alternating between `-high` and `high` in columns of
+    /// blocks, quantized with steps of 16: a deblocking threshold of 6
+    fn columns(high: f32) -> Coefficient {
+        component(4, 2, SampleFactor::One, [16.0; 64], |x, _| {
+            if (x / 8) % 2 == 0 { -high } else { high }
+        })
+    }
+
+    fn deblock(coef: Coefficient) -> Vec<f32> {
+        crate::deblock(vec![coef], 32, 16, 32 * 16).remove(0)
+    }
+
+    #[test]
+    fn smooths_blocking() {
+        let fdata = deblock(columns(2.0));
+        // across the first boundary, between columns 7 and 8 of a middle row
+        let row = &fdata[5 * 32..6 * 32];
+        assert!(row[8] - row[7] < 2.0, "{:?}", &row[4..12]);
+        assert!(
+            row[5..11].windows(2).all(|p| p[0] <= p[1]),
+            "{:?}",
+            &row[4..12]
+        );
+    }
+
+    #[test]
+    fn keeps_edges() {
+        let fdata = deblock(columns(10.0));
+        let row = &fdata[5 * 32..6 * 32];
+        assert!((row[8] - row[7] - 20.0).abs() < 1e-3, "{:?}", &row[4..12]);
+    }
+
+    #[test]
+    fn stays_in_the_quantization_box() {
+        let coef = columns(2.0);
+        assert_in_box(&deblock(coef.clone()), 32, &coef);
+    }
+}
@@ -0,0 +1,92 @@
+//! Small inputs shared by the unit tests
+
+use image::{ImageEncoder, codecs::jpeg::JpegEncoder};
+use zune_jpeg::sample_factor::SampleFactor;
+
+use crate::{jpeg::Coefficient, utils::dct::dct8x8s};
+
+/// A `width` x `height` JPEG of a colour gradient with some texture
+pub fn jpeg(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([
+            (x * 255 / width.max(1)) as u8,
+            (y * 255 / height.max(1)) as u8,
+            if (x / 3 + y / 5).is_multiple_of(2) {
+                64
+            } else {
+                192
+            },
+        ])
+    });
+    encode(&image)
+}
+
+/// `image` as a JPEG of quality 75
+pub fn encode(image: &image::RgbImage) -> Vec<u8> {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 75)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .expect("encoding to memory");
+    jpeg
+}
+
+/// `jpeg` with a thumbnail stored in an EXIF APP1 segment right after its SOI
+pub fn with_thumbnail(jpeg: &[u8], thumbnail: &[u8]) -> Vec<u8> {
+    let payload = [b"Exif\0\0".as_slice(), thumbnail].concat();
+    let len = u16::try_from(payload.len() + 2).expect("thumbnail fits in a segment");
+
+    let mut data = jpeg[..2].to_vec();
+    data.extend([0xFF, 0xE1]);
+    data.extend(len.to_be_bytes());
+    data.extend(payload);
+    data.extend(&jpeg[2..]);
+    data
+}
+
+/// A component of `block_w` x `block_h` blocks whose pixel at `(x, y)` of its
+/// own grid, centered around 0, is `pixel(x, y)`, quantized with `quant_table`
+pub fn component(
+    block_w: u32,
+    block_h: u32,
+    samp: SampleFactor,
+    quant_table: [f32; 64],
+    pixel: impl Fn(u32, u32) -> f32,
+) -> Coefficient {
+    let mut dct_coefs = Vec::with_capacity((block_w * block_h) as usize * 64);
+    for block_y in 0..block_h {
+        for block_x in 0..block_w {
+            let mut block: [f32; 64] = std::array::from_fn(|i| {
+                pixel(block_x * 8 + i as u32 % 8, block_y * 8 + i as u32 / 8)
+            });
+            dct8x8s(&mut block);
+            dct_coefs.extend((0..64).map(|i| (block[i] / quant_table[i]).round()));
+        }
+    }
+    Coefficient::new(block_w, block_h, samp, samp, dct_coefs, quant_table)
+        .expect("consistent component")
+}
+
+/// Checks that the 8x8 blocks of `fdata`, a plane of `stride` pixels wide at
+/// the resolution of `coef`, are in its quantization box
+pub fn assert_in_box(fdata: &[f32], stride: usize, coef: &Coefficient) {
+    for block_y in 0..coef.block_h as usize {
+        for block_x in 0..coef.block_w as usize {
+            let mut block: [f32; 64] = std::array::from_fn(|i| {
+                fdata[(block_y * 8 + i / 8) * stride + block_x * 8 + i % 8]
+            });
+            dct8x8s(&mut block);
+
+            let block_i = block_y * coef.block_w as usize + block_x;
+            for (i, value) in block.iter().enumerate() {
+                let quantized = coef.dct_coefs[block_i * 64 + i];
+                let distance = (value / coef.quant_table[i] - quantized).abs();
+                assert!(distance <= 0.5 + 1e-4, "block {block_i} coefficient {i}");
+            }
+        }
+    }
+}
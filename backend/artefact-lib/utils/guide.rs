@@ -0,0 +1,157 @@
+use crate::utils::{aux::Aux, macros::mul_add, store::Widen};
+
+/// Luma gradient magnitude at which a pixel is considered half-way an edge
+pub const EDGE_SCALE: f32 = 10.0;
+
+/// Computes the luma-guided chroma regularization term and its gradient
+///
+/// Chroma gradients are measured through a per-pixel metric built from the
+/// luma gradient: where luma is flat the whole chroma gradient is penalized,
+/// across a luma edge only the chroma change along the edge is penalized. This
+/// lets chroma edges snap to the full-resolution luma edges instead of bleeding
+/// over them.
+///
+/// Requires 3 channels, with luma in `auxs[0]`.
+pub fn compute_step_chroma_guide<S: Widen>(
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+    auxs: &mut [Aux<S>],
+    alpha: f32,
+) -> f64 {
+    debug_assert_eq!(auxs.len(), 3);
+
+    let mut guide = 0.0;
+
+    for curr_y in 0..max_rounded_px_h {
+        for curr_x in 0..max_rounded_px_w {
+            let curr_px_idx = (curr_y * max_rounded_px_w + curr_x) as usize;
+            let next_px_idx = curr_px_idx + 1;
+            let below_px_idx = curr_px_idx + max_rounded_px_w as usize;
+
+            let px_at_right_edge = curr_x >= max_rounded_px_w - 1;
+            let px_at_bottom_edge = curr_y >= max_rounded_px_h - 1;
+
+            let forward_diff = |fdata: &[f32]| {
+                (
+                    if px_at_right_edge {
+                        0.0
+                    } else {
+                        fdata[next_px_idx] - fdata[curr_px_idx]
+                    },
+                    if px_at_bottom_edge {
+                        0.0
+                    } else {
+                        fdata[below_px_idx] - fdata[curr_px_idx]
+                    },
+                )
+            };
+
+            // luma edge strength and direction
+            let (luma_x, luma_y) = forward_diff(&auxs[0].fdata);
+            let luma_norm_sq = mul_add!(luma_x, luma_x, luma_y * luma_y);
+            let flatness = 1.0 / (1.0 + luma_norm_sq / (EDGE_SCALE * EDGE_SCALE));
+
+            // unit tangent of the luma edge
+            let (tan_x, tan_y) = if luma_norm_sq == 0.0 {
+                (0.0, 0.0)
+            } else {
+                let luma_norm = luma_norm_sq.sqrt();
+                (-luma_y / luma_norm, luma_x / luma_norm)
+            };
+
+            // metric = flatness * I + (1 - flatness) * t * t^T
+            let metric = |g_x: f32, g_y: f32| {
+                let along = mul_add!(g_x, tan_x, g_y * tan_y) * (1.0 - flatness);
+                (
+                    mul_add!(flatness, g_x, along * tan_x),
+                    mul_add!(flatness, g_y, along * tan_y),
+                )
+            };
+
+            let mut g_xs = [0.0; 2];
+            let mut g_ys = [0.0; 2];
+            let mut m_xs = [0.0; 2];
+            let mut m_ys = [0.0; 2];
+            let mut g_norm = 0.0;
+            for c in 0..2 {
+                (g_xs[c], g_ys[c]) = forward_diff(&auxs[c + 1].fdata);
+                (m_xs[c], m_ys[c]) = metric(g_xs[c], g_ys[c]);
+                g_norm += mul_add!(g_xs[c], m_xs[c], g_ys[c] * m_ys[c]);
+            }
+            let g_norm = g_norm.sqrt();
+
+            guide += f64::from(alpha * g_norm);
+
+            if g_norm == 0.0 {
+                continue;
+            }
+
+            // compute derivatives
+            for c in 0..2 {
+                let aux = &mut auxs[c + 1];
+
+                aux.obj_gradient[curr_px_idx] += alpha * -(m_xs[c] + m_ys[c]) / g_norm;
+
+                if !px_at_right_edge {
+                    aux.obj_gradient[next_px_idx] += alpha * m_xs[c] / g_norm;
+                }
+
+                if !px_at_bottom_edge {
+                    aux.obj_gradient[below_px_idx] += alpha * m_ys[c] / g_norm;
+                }
+            }
+        }
+    }
+
+    guide
+}
+
+#[cfg(test)]
+mod tests {
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    use crate::{
+        Artefact, Jpeg, JpegSource, PlaneResolution, ValueCollection, utils::fixtures::component,
+    };
+
+    /// Share of the horizontal chroma change of a middle row that happens away
+    /// from the luma edge
+    fn chroma_change_off_edge(chroma_guide: f32) -> f32 {
+        // 4:2:0 with a sharp vertical edge between luma columns 12 and 13,
+        // which falls inside chroma column 6
+        let step = |x: u32, high: f32| if x < 13 { -high } else { high };
+        let jpeg = Jpeg::new(
+            32,
+            16,
+            vec![
+                component(4, 2, SampleFactor::One, [2.0; 64], |x, _| step(x, 60.0)),
+                component(2, 1, SampleFactor::Two, [16.0; 64], |x, _| {
+                    f32::midpoint(step(x * 2, 40.0), step(x * 2 + 1, 40.0))
+                }),
+                component(2, 1, SampleFactor::Two, [16.0; 64], |_, _| 0.0),
+            ],
+        )
+        .expect("consistent components");
+
+        let planes = Artefact::default()
+            .chroma_guide(chroma_guide)
+            .iterations(ValueCollection::ForAll(200))
+            .process_source_planes(JpegSource::Coefficients(jpeg), PlaneResolution::Full)
+            .expect("processing the image");
+
+        let row = &planes[1].data[8 * 32..9 * 32];
+        let changes = row.windows(2).map(|p| (p[1] - p[0]).abs());
+        let at_edge = changes.clone().nth(12).expect("inside the row");
+        1.0 - at_edge / changes.sum::<f32>()
+    }
+
+    #[test]
+    fn aligns_chroma_edges_with_luma() {
+        let guided = chroma_change_off_edge(1.0);
+        let unguided = chroma_change_off_edge(0.0);
+        assert!(
+            guided < unguided * 0.75,
+            "{guided} of the chroma change is away from the luma edge with guidance, {unguided} without"
+        );
+    }
+}
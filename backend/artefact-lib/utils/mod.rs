@@ -1,6 +1,15 @@
 pub mod aux;
 pub mod boxing;
 pub mod dct;
+pub mod deblock;
+#[cfg(test)]
+pub mod fixtures;
+pub mod guide;
 pub mod macros;
+pub mod npy;
+pub mod pyramid;
+pub mod store;
+pub mod temporal;
+pub mod timing;
 #[cfg(feature = "simd")]
 pub mod traits;
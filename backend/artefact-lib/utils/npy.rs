@@ -0,0 +1,242 @@
+//! Minimal reader and writer for the numpy `.npy` format (version 1.0)
+//!
+//! Only little-endian, C-ordered arrays of plain numbers are supported, which
+//! is what `numpy.save` produces by default.
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// An element type that can be written to a `.npy` file
+pub trait NpyElement: Copy {
+    /// Type string of the element in the `.npy` header
+    const DESCR: &'static str;
+
+    fn write_le(self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_npy_element {
+    ($($t:ty => $descr:literal),+) => {
+        $(
+            impl NpyElement for $t {
+                const DESCR: &'static str = $descr;
+
+                fn write_le(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )+
+    };
+}
+
+impl_npy_element!(u8 => "|u1", i16 => "<i2", u16 => "<u2", u32 => "<u4", f32 => "<f4");
+
+/// Serialize an array to the `.npy` format
+///
+/// # Panics
+/// if the number of elements does not match the shape
+pub fn to_npy<T: NpyElement>(shape: &[usize], data: &[T]) -> Vec<u8> {
+    assert_eq!(shape.iter().product::<usize>(), data.len());
+
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+        T::DESCR
+    );
+
+    // magic + version + header length + header + '\n' is aligned to 64 bytes
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(MAGIC.len() + 4 + header.len() + data.len() * 4);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for value in data {
+        value.write_le(&mut out);
+    }
+    out
+}
+
+/// An array read from a `.npy` file, widened to `f64`
+#[derive(Debug)]
+pub struct NpyArray {
+    pub shape: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+/// Deserialize an array from the `.npy` format
+///
+/// # Errors
+/// Returns an error if the data is not a `.npy` file, or if the array is
+/// Fortran-ordered, big-endian or not made of plain numbers.
+pub fn from_npy(bytes: &[u8]) -> Result<NpyArray, String> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err("Not a .npy file".to_string());
+    }
+
+    let (header_len, header_start) = match bytes[6] {
+        1 => (usize::from(u16::from_le_bytes([bytes[8], bytes[9]])), 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        version => return Err(format!("Unsupported .npy version {version}")),
+    };
+    let header = bytes
+        .get(header_start..header_start + header_len)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or("Invalid .npy header")?;
+    let data = &bytes[header_start + header_len..];
+
+    let descr = header_value(header, "descr")
+        .and_then(|v| v.strip_prefix('\'')?.split('\'').next())
+        .ok_or("Missing 'descr' in .npy header")?;
+
+    if header_value(header, "fortran_order").is_some_and(|v| v.starts_with("True")) {
+        return Err("Fortran-ordered arrays are not supported".to_string());
+    }
+
+    let shape = header_value(header, "shape")
+        .and_then(|v| v.strip_prefix('(')?.split(')').next())
+        .ok_or("Missing 'shape' in .npy header")?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| {
+            dim.parse::<usize>()
+                .map_err(|e| format!("Invalid shape: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = shape
+        .iter()
+        .try_fold(1_usize, |count, &dim| count.checked_mul(dim))
+        .ok_or("Shape of the .npy array is too large")?;
+
+    macro_rules! read {
+        ($t:ty) => {{
+            const SIZE: usize = std::mem::size_of::<$t>();
+            if count.checked_mul(SIZE).is_none_or(|size| data.len() < size) {
+                return Err("Truncated .npy data".to_string());
+            }
+            data.chunks_exact(SIZE)
+                .take(count)
+                .map(|chunk| {
+                    f64::from(<$t>::from_le_bytes(
+                        chunk.try_into().expect("chunk of the element size"),
+                    ))
+                })
+                .collect()
+        }};
+    }
+
+    let data = match descr {
+        "|u1" | "<u1" => read!(u8),
+        "|i1" | "<i1" => read!(i8),
+        "<i2" => read!(i16),
+        "<u2" => read!(u16),
+        "<i4" => read!(i32),
+        "<u4" => read!(u32),
+        "<f4" => read!(f32),
+        "<f8" => read!(f64),
+        _ => return Err(format!("Unsupported .npy dtype '{descr}'")),
+    };
+
+    Ok(NpyArray { shape, data })
+}
+
+/// The text following `'key':` in a `.npy` header dict
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}'"))? + key.len() + 2;
+    Some(header[start..].trim_start().strip_prefix(':')?.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_npy, to_npy};
+
+    #[test]
+    fn round_trip() {
+        let data = [0_i16, -1, 2, i16::MIN, i16::MAX, 5];
+        let npy = to_npy(&[2, 3], &data);
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        assert_eq!((npy.len() - data.len() * 2) % 64, 0, "header is aligned");
+
+        let array = from_npy(&npy).expect("valid .npy");
+        assert_eq!(array.shape, [2, 3]);
+        assert_eq!(array.data, data.map(f64::from));
+    }
+
+    #[test]
+    fn round_trip_1d() {
+        let data = [0.5_f32, -1.25, 3.0];
+        let array = from_npy(&to_npy(&[3], &data)).expect("valid .npy");
+        assert_eq!(array.shape, [3]);
+        assert_eq!(array.data, data.map(f64::from));
+
+        let array = from_npy(&to_npy(&[2], &[7_u8, 255])).expect("valid .npy");
+        assert_eq!(array.data, [7.0, 255.0]);
+    }
+
+    /// A `.npy` file with a hand written header
+    fn npy(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        npy.extend_from_slice(header.as_bytes());
+        npy.extend_from_slice(data);
+        npy
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(from_npy(b"not a npy file").is_err());
+        assert!(
+            from_npy(&npy(
+                "{'descr': '<f4', 'fortran_order': True, 'shape': (1,), }",
+                &[0; 4]
+            ))
+            .is_err()
+        );
+        assert!(
+            from_npy(&npy(
+                "{'descr': '>f4', 'fortran_order': False, 'shape': (1,), }",
+                &[0; 4]
+            ))
+            .is_err()
+        );
+        assert!(
+            from_npy(&npy(
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }",
+                &[0; 4]
+            ))
+            .is_err(),
+            "truncated data"
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_shape() {
+        let huge = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}",
+            usize::MAX / 2,
+            3
+        );
+        assert!(from_npy(&npy(&huge, &[0; 8])).is_err());
+
+        let huge = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({},), }}",
+            usize::MAX / 4
+        );
+        assert!(from_npy(&npy(&huge, &[0; 8])).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+use crate::{
+    jpeg::Coefficient,
+    utils::{dct::dct8x8s, macros::mul_add},
+};
+
+/// Halve the resolution of all components of an image
+///
+/// The coarse block counts are rounded up so that the subsampled components
+/// still line up with the full resolution ones.
+pub fn downsample(coefs: &[Coefficient]) -> Vec<Coefficient> {
+    let max_h_samp = coefs
+        .iter()
+        .map(|c| c.horizontal_samp_factor.u32())
+        .max()
+        .unwrap_or(1);
+    let max_v_samp = coefs
+        .iter()
+        .map(|c| c.vertical_samp_factor.u32())
+        .max()
+        .unwrap_or(1);
+
+    let coarse_px_w = coefs
+        .iter()
+        .map(|c| c.block_w.div_ceil(2) * 8 * c.horizontal_samp_factor.u32())
+        .max()
+        .unwrap_or(0)
+        .next_multiple_of(8 * max_h_samp);
+    let coarse_px_h = coefs
+        .iter()
+        .map(|c| c.block_h.div_ceil(2) * 8 * c.vertical_samp_factor.u32())
+        .max()
+        .unwrap_or(0)
+        .next_multiple_of(8 * max_v_samp);
+
+    coefs
+        .iter()
+        .map(|c| {
+            downsample_component(
+                c,
+                coarse_px_w / (8 * c.horizontal_samp_factor.u32()),
+                coarse_px_h / (8 * c.vertical_samp_factor.u32()),
+            )
+        })
+        .collect()
+}
+
+/// Halve the resolution of a component, keeping only its low frequencies
+///
+/// Every coarse 8x8 block covers 2x2 original blocks. Each original block is
+/// shrunk to 4x4 pixels from its 4x4 lowest frequencies, the 4 results are
+/// stitched together and transformed back. The coarse quantization table
+/// reuses the original step of the matching (halved) frequency, so the DC
+/// and low-frequency constraints carry over to the coarse problem.
+fn downsample_component(coef: &Coefficient, block_w: u32, block_h: u32) -> Coefficient {
+    let block_count = block_w * block_h;
+
+    let mut quant_table = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            quant_table[v * 8 + u] = coef.quant_table[(v / 2) * 8 + u / 2];
+        }
+    }
+
+    // 8-point to 4-point DCT: averaging pairs of samples of the 8-point basis
+    // gives the 4-point basis scaled by cos(pi * k / 16) / sqrt(2)
+    let scale: [f32; 4] = std::array::from_fn(|k| (PI * k as f32 / 16.0).cos() * FRAC_1_SQRT_2);
+
+    let mut dct_coefs = vec![0.0; block_count as usize * 64];
+
+    for block_y in 0..block_h {
+        for block_x in 0..block_w {
+            let mut block = [0.0; 64];
+
+            for sub_y in 0..2 {
+                for sub_x in 0..2 {
+                    // replicate the last block past the edge
+                    let fine_x = (block_x * 2 + sub_x).min(coef.block_w - 1);
+                    let fine_y = (block_y * 2 + sub_y).min(coef.block_h - 1);
+                    let fine_i = (fine_y * coef.block_w + fine_x) as usize;
+                    let fine = &coef.dct_coefs[fine_i * 64..(fine_i + 1) * 64];
+
+                    let mut sub = [0.0; 16];
+                    for v in 0..4 {
+                        for u in 0..4 {
+                            sub[v * 4 + u] =
+                                fine[v * 8 + u] * coef.quant_table[v * 8 + u] * scale[u] * scale[v];
+                        }
+                    }
+                    idct4x4(&mut sub);
+
+                    for in_y in 0..4 {
+                        for in_x in 0..4 {
+                            let y = sub_y as usize * 4 + in_y;
+                            let x = sub_x as usize * 4 + in_x;
+                            block[y * 8 + x] = sub[in_y * 4 + in_x];
+                        }
+                    }
+                }
+            }
+
+            dct8x8s(&mut block);
+
+            let i = (block_y * block_w + block_x) as usize;
+            for j in 0..64 {
+                dct_coefs[i * 64 + j] = block[j] / quant_table[j];
+            }
+        }
+    }
+
+    Coefficient {
+        rounded_px_w: block_w * 8,
+        rounded_px_h: block_h * 8,
+        rounded_px_count: block_count * 64,
+        block_w,
+        block_h,
+        block_count,
+        horizontal_samp_factor: coef.horizontal_samp_factor,
+        vertical_samp_factor: coef.vertical_samp_factor,
+        dct_coefs,
+        quant_table,
+    }
+}
+
+/// Iterations of a level of the pyramid, half of them when it starts from the
+/// solution of a coarser level
+pub const fn level_iterations(iterations: usize, coarse_init: bool) -> usize {
+    if coarse_init {
+        iterations.div_ceil(2)
+    } else {
+        iterations
+    }
+}
+
+/// Bilinear 2x upsampling of a coarse solution, cropped to the target size
+pub fn upsample(
+    fdata: &[f32],
+    coarse_px_w: u32,
+    coarse_px_h: u32,
+    max_rounded_px_w: u32,
+    max_rounded_px_h: u32,
+) -> Vec<f32> {
+    let mut output = Vec::with_capacity((max_rounded_px_w * max_rounded_px_h) as usize);
+
+    let sample = |pos: u32, len: u32| {
+        let pos = (f32::midpoint(pos as f32, 0.5) - 0.5).clamp(0.0, (len - 1) as f32);
+        let low = pos.floor() as usize;
+        let high = (low + 1).min(len as usize - 1);
+        (low, high, pos - low as f32)
+    };
+
+    for y in 0..max_rounded_px_h {
+        let (y0, y1, fy) = sample(y, coarse_px_h);
+        for x in 0..max_rounded_px_w {
+            let (x0, x1, fx) = sample(x, coarse_px_w);
+            let at = |y: usize, x: usize| fdata[y * coarse_px_w as usize + x];
+
+            let top = mul_add!(at(y0, x1) - at(y0, x0), fx, at(y0, x0));
+            let bottom = mul_add!(at(y1, x1) - at(y1, x0), fx, at(y1, x0));
+            output.push(mul_add!(bottom - top, fy, top));
+        }
+    }
+
+    output
+}
+
+/// Orthonormal 4x4 inverse DCT
+fn idct4x4(a: &mut [f32; 16]) {
+    let basis: [[f32; 4]; 4] = std::array::from_fn(|n| {
+        std::array::from_fn(|k| {
+            let c = if k == 0 { FRAC_1_SQRT_2 } else { 1.0 };
+            c * FRAC_1_SQRT_2 * (PI * (2 * n + 1) as f32 * k as f32 / 8.0).cos()
+        })
+    });
+
+    let input = *a;
+    for y in 0..4 {
+        for x in 0..4 {
+            let mut sum = 0.0;
+            for v in 0..4 {
+                for u in 0..4 {
+                    sum += basis[y][v] * basis[x][u] * input[v * 4 + u];
+                }
+            }
+            a[y * 4 + x] = sum;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zune_jpeg::sample_factor::SampleFactor;
+
+    use super::level_iterations;
+    use crate::{
+        Artefact, Jpeg, JpegSource, PlaneResolution, ValueCollection,
+        utils::fixtures::{assert_in_box, component},
+    };
+
+    #[test]
+    fn stays_in_the_quantization_box() {
+        // Smooth shading with a few sharp features the coarse level misses
+        let coef = component(6, 4, SampleFactor::One, [12.0; 64], |x, y| {
+            let shading = (x * 2) as f32 - (y * 3) as f32 / 2.0;
+            if (x / 3 + y / 5) % 4 == 0 {
+                shading + 40.0
+            } else {
+                shading
+            }
+        });
+        let jpeg = Jpeg::new(48, 32, vec![coef.clone()]).expect("consistent components");
+
+        // A single iteration, so that the output is the projected upsampled
+        // coarse solution after one step
+        let planes = Artefact::default()
+            .pyramid(2)
+            .iterations(ValueCollection::ForAll(1))
+            .process_source_planes(JpegSource::Coefficients(jpeg), PlaneResolution::Full)
+            .expect("processing the image");
+
+        assert_in_box(&planes[0].data, 48, &coef);
+    }
+
+    #[test]
+    fn halves_iterations_from_a_coarser_level() {
+        assert_eq!(level_iterations(100, false), 100);
+        assert_eq!(level_iterations(100, true), 50);
+        assert_eq!(level_iterations(5, true), 3);
+    }
+}
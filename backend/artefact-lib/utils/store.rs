@@ -0,0 +1,61 @@
+/// Element type of the large working buffers that are only read back by the
+/// compute kernels: `Aux::cos`, `Aux::fista` and `Aux::pixel_diff`
+///
+/// `f32` keeps them exact. `f16`, selected with [`crate::Precision::Half`],
+/// halves their size: they are widened to `f32` right where a kernel loads
+/// them and all arithmetic still happens in `f32`, only the stored values are
+/// rounded (to 11 significant bits, which is ~0.06 at the top of the pixel
+/// range).
+pub trait Widen: Copy + Send + Sync + 'static {
+    fn widen(self) -> f32;
+    fn narrow(value: f32) -> Self;
+
+    /// Widen the first `target.len()` elements of `source` into `target`
+    ///
+    /// # Panics
+    /// if `source` is shorter than `target`
+    fn widen_slice(source: &[Self], target: &mut [f32]);
+
+    /// Narrow `source` into `target`
+    ///
+    /// # Panics
+    /// if the slices are not of the same length
+    fn narrow_slice(source: &[f32], target: &mut [Self]);
+}
+
+impl Widen for f32 {
+    fn widen(self) -> f32 {
+        self
+    }
+    fn narrow(value: f32) -> Self {
+        value
+    }
+    fn widen_slice(source: &[Self], target: &mut [f32]) {
+        target.copy_from_slice(&source[..target.len()]);
+    }
+    fn narrow_slice(source: &[f32], target: &mut [Self]) {
+        target.copy_from_slice(source);
+    }
+}
+
+impl Widen for half::f16 {
+    fn widen(self) -> f32 {
+        self.to_f32()
+    }
+    fn narrow(value: f32) -> Self {
+        Self::from_f32(value)
+    }
+    fn widen_slice(source: &[Self], target: &mut [f32]) {
+        use half::slice::HalfFloatSliceExt;
+        source[..target.len()].convert_to_f32_slice(target);
+    }
+    fn narrow_slice(source: &[f32], target: &mut [Self]) {
+        use half::slice::HalfFloatSliceExt;
+        target.convert_from_f32_slice(source);
+    }
+}
+
+/// Narrow a whole `f32` buffer into a new storage buffer
+pub fn narrow<S: Widen>(source: &[f32]) -> Vec<S> {
+    source.iter().copied().map(S::narrow).collect()
+}
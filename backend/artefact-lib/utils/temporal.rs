@@ -0,0 +1,81 @@
+use crate::utils::{aux::Aux, store::Widen};
+
+/// Solution of the previous frame of a sequence, and how strongly the current
+/// one is pulled towards it
+#[derive(Debug, Clone, Copy)]
+pub struct Temporal<'a> {
+    /// Output planes of the previous frame, one per solved component
+    pub previous: &'a [Vec<f32>],
+    pub weight: f32,
+}
+
+/// Computes the gradient of the temporal smoothness term
+/// `weight / 2 * ||fdata - previous||^2`
+///
+/// Pixels that did not change between frames stay put instead of drifting to
+/// a slightly different point of their quantization box every frame, which is
+/// what shows up as flicker. Moving content is still held by the projection
+/// onto the current frame's coefficients.
+pub fn compute_step_temporal<S: Widen>(auxs: &mut [Aux<S>], temporal: Temporal) {
+    for (aux, previous) in auxs.iter_mut().zip(temporal.previous) {
+        for ((gradient, &fdata), &previous) in
+            aux.obj_gradient.iter_mut().zip(&aux.fdata).zip(previous)
+        {
+            *gradient += temporal.weight * (fdata - previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Artefact, Jpeg, JpegSource, ValueCollection, utils::fixtures};
+
+    /// Two frames of the same textured gradient with a little sensor noise,
+    /// which each JPEG quantizes slightly differently
+    fn frames() -> [Jpeg; 2] {
+        [0, 1].map(|frame: u32| {
+            let jpeg = fixtures::encode(&image::RgbImage::from_fn(32, 32, |x, y| {
+                let noise = ((x * 7 + y * 13 + frame * 29) % 5) as u8 * 4;
+                let level = (x * 5 + y * 2) as u8 + noise;
+                image::Rgb([level, level / 2 + 60, 200 - level / 2])
+            }));
+            Jpeg::from(JpegSource::Buffer(jpeg)).expect("valid JPEG")
+        })
+    }
+
+    /// Root mean square difference between the planes of two outputs
+    fn distance(a: &[Vec<f32>], b: &[Vec<f32>]) -> f32 {
+        let (sum, count) = a
+            .iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .fold((0.0, 0), |(sum, count), (a, b)| {
+                ((a - b).mul_add(a - b, sum), count + 1)
+            });
+        (sum / count as f32).sqrt()
+    }
+
+    #[test]
+    fn pulls_frames_towards_the_previous_one() {
+        let artefact = Artefact::default()
+            .iterations(ValueCollection::ForAll(50))
+            .temporal_weight(0.5)
+            .warm_start(true);
+        let [first, second] = frames();
+
+        let solve = |jpeg: &Jpeg, previous: Option<&[Vec<f32>]>| {
+            artefact
+                .process_jpeg(jpeg.clone(), previous, None, None)
+                .expect("processing the frame")
+                .1
+        };
+        let previous = solve(&first, None);
+
+        let warm = distance(&solve(&second, Some(&previous)), &previous);
+        let cold = distance(&solve(&second, None), &previous);
+        assert!(
+            warm < cold * 0.9,
+            "{warm} from the previous frame with the temporal prior, {cold} without"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Stages of the processing timed for benchmarks, see [`Timings`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Conversion of the decoded coefficients to the pipeline layout
+    CoefConversion,
+    /// Working buffers, including the plain decode
+    AuxInit,
+    /// DCT coefficient distance term
+    Prob,
+    /// First order total variation term
+    Tv,
+    /// Second order total generalized variation term
+    Tgv,
+    /// Projection back into the quantization intervals
+    Projection,
+    /// YCbCr to RGB
+    ColorConversion,
+}
+
+impl Stage {
+    pub const ALL: [Self; 7] = [
+        Self::CoefConversion,
+        Self::AuxInit,
+        Self::Prob,
+        Self::Tv,
+        Self::Tgv,
+        Self::Projection,
+        Self::ColorConversion,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::CoefConversion => "coef_conversion",
+            Self::AuxInit => "aux_init",
+            Self::Prob => "prob",
+            Self::Tv => "tv",
+            Self::Tgv => "tgv",
+            Self::Projection => "projection",
+            Self::ColorConversion => "color_conversion",
+        }
+    }
+}
+
+/// Time spent in each stage of one processing call, see
+/// [`Artefact::process_source_timed`](crate::Artefact::process_source_timed)
+///
+/// Times are summed over iterations and components, so components processed
+/// in parallel count once each.
+#[derive(Debug, Default)]
+pub struct Timings {
+    /// Nanoseconds spent in each stage, indexed by [`Stage`]
+    nanos: [AtomicU64; Stage::ALL.len()],
+}
+
+impl Timings {
+    pub(crate) fn record(&self, stage: Stage, elapsed: Duration) {
+        self.nanos[stage as usize].fetch_add(
+            u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Time spent in each stage, in the order of [`Stage::ALL`]
+    #[must_use]
+    pub fn stages(&self) -> Vec<(Stage, Duration)> {
+        Stage::ALL
+            .iter()
+            .map(|&stage| {
+                let nanos = self.nanos[stage as usize].load(Ordering::Relaxed);
+                (stage, Duration::from_nanos(nanos))
+            })
+            .collect()
+    }
+}
+
+/// Number of threads the solver runs on
+#[must_use]
+pub fn thread_count() -> usize {
+    rayon::current_num_threads()
+}
+
+/// Evaluate an expression, adding the time it takes to a [`Stage`] of the
+/// [`Timings`] if any
+///
+/// The clock is only read when timing, it is not available on every target.
+macro_rules! timed {
+    ($timings:expr, $stage:expr, $body:expr) => {{
+        match $timings {
+            Some(timings) => {
+                let start = std::time::Instant::now();
+                let result = $body;
+                crate::utils::timing::Timings::record(timings, $stage, start.elapsed());
+                result
+            }
+            None => $body,
+        }
+    }};
+}
+
+pub(crate) use timed;
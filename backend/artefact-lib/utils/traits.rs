@@ -7,6 +7,8 @@ use std::{
 
 use paste::paste;
 
+use crate::utils::store::Widen;
+
 macro_rules! def_std_simd_type {
     ($($width:literal),+) => {
         paste! {
@@ -20,17 +22,17 @@ macro_rules! def_std_simd_type {
 def_std_simd_type!(8, 16, 32, 64);
 
 pub trait WriteTo {
-    fn write_to(&self, target: &mut [f32]);
-    fn write_partial_to(&self, target: &mut [f32], range: RangeInclusive<usize>);
+    fn write_to<E: Widen>(&self, target: &mut [E]);
+    fn write_partial_to<E: Widen>(&self, target: &mut [E], range: RangeInclusive<usize>);
 }
 
 impl WriteTo for wide::f32x8 {
-    fn write_to(&self, target: &mut [f32]) {
-        target.copy_from_slice(self.as_array_ref());
+    fn write_to<E: Widen>(&self, target: &mut [E]) {
+        E::narrow_slice(self.as_array_ref(), target);
     }
 
-    fn write_partial_to(&self, target: &mut [f32], range: RangeInclusive<usize>) {
-        target.copy_from_slice(&self.as_array_ref()[range]);
+    fn write_partial_to<E: Widen>(&self, target: &mut [E], range: RangeInclusive<usize>) {
+        E::narrow_slice(&self.as_array_ref()[range], target);
     }
 }
 
@@ -38,11 +40,11 @@ macro_rules! gen_write_to {
     ($($width:literal),+) => {
         $(paste! {
             impl WriteTo for [<StdF32x $width>] {
-                fn write_to(&self, target: &mut [f32]) {
-                    target.copy_from_slice(self.as_array());
+                fn write_to<E: Widen>(&self, target: &mut [E]) {
+                    E::narrow_slice(self.as_array(), target);
                 }
-                fn write_partial_to(&self, target: &mut [f32], range: RangeInclusive<usize>) {
-                    target.copy_from_slice(&self.as_array()[range]);
+                fn write_partial_to<E: Widen>(&self, target: &mut [E], range: RangeInclusive<usize>) {
+                    E::narrow_slice(&self.as_array()[range], target);
                 }
             }
         })+
@@ -56,33 +58,35 @@ pub trait FromSlice {
     ///
     /// # Panics
     /// if the slice is not of length 8
-    fn from_slc(slc: &[f32]) -> Self;
+    fn from_slc<E: Widen>(slc: &[E]) -> Self;
 
     /// Right-pad a `less-than-8-elem-slice` with `0`s to create a `f32x8`
     ///
     /// # Panics
     /// if the slice is longer than 8
-    fn from_short_slc(slc: &[f32]) -> Self;
+    fn from_short_slc<E: Widen>(slc: &[E]) -> Self;
 
     /// Create a `f32x8` from a slice with a range
     ///
     /// # Panics
     /// if the range larger than 8 or out of bounds
-    fn from_range_slc(slc: &[f32], range: RangeInclusive<usize>) -> Self;
+    fn from_range_slc<E: Widen>(slc: &[E], range: RangeInclusive<usize>) -> Self;
 }
 
 impl FromSlice for wide::f32x8 {
-    fn from_slc(slc: &[f32]) -> Self {
-        Self::from(slc)
+    fn from_slc<E: Widen>(slc: &[E]) -> Self {
+        let mut tmp = [0.0; 8];
+        E::widen_slice(slc, &mut tmp);
+        Self::from(tmp)
     }
-    fn from_short_slc(slc: &[f32]) -> Self {
+    fn from_short_slc<E: Widen>(slc: &[E]) -> Self {
         let mut tmp = [0.0; 8];
-        tmp[..slc.len()].copy_from_slice(slc);
+        E::widen_slice(slc, &mut tmp[..slc.len()]);
         Self::from(tmp)
     }
-    fn from_range_slc(slc: &[f32], range: RangeInclusive<usize>) -> Self {
+    fn from_range_slc<E: Widen>(slc: &[E], range: RangeInclusive<usize>) -> Self {
         let mut tmp = [0.0; 8];
-        tmp[range].copy_from_slice(slc);
+        E::widen_slice(slc, &mut tmp[range]);
         Self::from(tmp)
     }
 }
@@ -91,16 +95,20 @@ macro_rules! gen_from_slice {
     ($($width:literal),+) => {
         $(paste! {
             impl FromSlice for [<StdF32x $width>] {
-                fn from_slc(slice: &[f32]) -> Self {
-                    std::simd::[<f32x $width>]::from_slice(slice)
+                fn from_slc<E: Widen>(slice: &[E]) -> Self {
+                    let mut tmp = [0.0; $width];
+                    E::widen_slice(slice, &mut tmp);
+                    std::simd::[<f32x $width>]::from_array(tmp)
                 }
-                fn from_short_slc(slice: &[f32]) -> Self {
-                    std::simd::[<f32x $width>]::load_or_default(slice)
+                fn from_short_slc<E: Widen>(slice: &[E]) -> Self {
+                    let mut tmp = [0.0; $width];
+                    E::widen_slice(slice, &mut tmp[..slice.len()]);
+                    std::simd::[<f32x $width>]::from_array(tmp)
                 }
-                fn from_range_slc(slice: &[f32], range: RangeInclusive<usize>) -> Self {
-                    let mut tmp = std::simd::[<f32x $width>]::splat(0.0);
-                    tmp[range].copy_from_slice(slice);
-                    tmp
+                fn from_range_slc<E: Widen>(slice: &[E], range: RangeInclusive<usize>) -> Self {
+                    let mut tmp = [0.0; $width];
+                    E::widen_slice(slice, &mut tmp[range]);
+                    std::simd::[<f32x $width>]::from_array(tmp)
                 }
             }
         })+
@@ -0,0 +1,211 @@
+use crate::{
+    jpeg::{Coefficient, Jpeg, JpegSource},
+    utils::{boxing::box_block, dct::dct8x8s, macros::mul_add},
+};
+
+/// Outcome of [`verify`] for one component
+#[derive(Debug, Clone)]
+pub struct ComponentReport {
+    pub block_w: u32,
+    pub block_h: u32,
+
+    /// Number of coefficients outside their quantization interval, per block
+    /// in raster order. `None` for blocks that extend past the image edge:
+    /// their padding is not part of the output image, so they cannot be checked.
+    pub blocks: Vec<Option<u32>>,
+
+    /// Largest distance between a re-encoded coefficient and its quantized
+    /// value, in quantization steps. Anything above 0.5 is a violation.
+    pub max_distance: f32,
+}
+
+impl ComponentReport {
+    /// Number of checked blocks
+    #[must_use]
+    pub fn checked_blocks(&self) -> usize {
+        self.blocks.iter().flatten().count()
+    }
+
+    /// Number of blocks with at least one violation
+    #[must_use]
+    pub fn violating_blocks(&self) -> usize {
+        self.blocks.iter().flatten().filter(|&&n| n > 0).count()
+    }
+
+    /// Number of coefficients outside their quantization interval
+    #[must_use]
+    pub fn violations(&self) -> usize {
+        self.blocks.iter().flatten().map(|&n| n as usize).sum()
+    }
+}
+
+/// Outcome of [`verify`], one report per JPEG component
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub components: Vec<ComponentReport>,
+}
+
+impl VerifyReport {
+    /// Number of coefficients outside their quantization interval
+    #[must_use]
+    pub fn violations(&self) -> usize {
+        self.components
+            .iter()
+            .map(ComponentReport::violations)
+            .sum()
+    }
+
+    /// Whether the image re-encodes to the coefficients of the JPEG
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.violations() == 0
+    }
+}
+
+/// Check that an image re-encodes to the quantized coefficients of a JPEG
+///
+/// Runs the forward path of the encoder on `image` (RGB -> YCbCr, chroma
+/// downsampling, DCT, division by the quantization table) and compares every
+/// coefficient with the one stored in the JPEG. Blocks that extend past the
+/// image edge are skipped.
+///
+/// # Errors
+/// Returns an error if reading the JPEG fails or if the image size does not
+/// match the JPEG.
+pub fn verify(image: &image::RgbImage, source: JpegSource) -> Result<VerifyReport, String> {
+    let jpeg = Jpeg::from(source).map_err(|e| format!("Failed to read JPEG: {e}"))?;
+
+    if image.dimensions() != (jpeg.real_px_w, jpeg.real_px_h) {
+        return Err(format!(
+            "Image size {}x{} does not match JPEG size {}x{}",
+            image.width(),
+            image.height(),
+            jpeg.real_px_w,
+            jpeg.real_px_h
+        ));
+    }
+
+    // RGB -> YCbCr, centered around 0
+    let mut planes = vec![Vec::with_capacity(image.len() / 3); jpeg.coefs.len()];
+    for px in image.pixels() {
+        let [r, g, b] = px.0.map(f32::from);
+
+        planes[0].push(mul_add!(0.299_f32, r, mul_add!(0.587_f32, g, 0.114 * b)) - 128.0);
+        if planes.len() == 3 {
+            planes[1].push(mul_add!(
+                -0.168_736_f32,
+                r,
+                mul_add!(-0.331_264_f32, g, 0.5 * b)
+            ));
+            planes[2].push(mul_add!(
+                0.5_f32,
+                r,
+                mul_add!(-0.418_688_f32, g, -0.081_312 * b)
+            ));
+        }
+    }
+
+    Ok(VerifyReport {
+        components: jpeg
+            .coefs
+            .iter()
+            .zip(planes)
+            .map(|(coef, plane)| verify_component(coef, &plane, jpeg.real_px_w, jpeg.real_px_h))
+            .collect(),
+    })
+}
+
+fn verify_component(
+    coef: &Coefficient,
+    plane: &[f32],
+    real_px_w: u32,
+    real_px_h: u32,
+) -> ComponentReport {
+    let h_samp = coef.horizontal_samp_factor.u32();
+    let v_samp = coef.vertical_samp_factor.u32();
+
+    // Downsample by averaging, the padding is never read
+    let mut component = vec![0.0; coef.rounded_px_count as usize];
+    for cy in 0..coef.rounded_px_h.min(real_px_h.div_ceil(v_samp)) {
+        for cx in 0..coef.rounded_px_w.min(real_px_w.div_ceil(h_samp)) {
+            let mut sum = 0.0;
+            let mut count = 0.0;
+            for y in cy * v_samp..((cy + 1) * v_samp).min(real_px_h) {
+                for x in cx * h_samp..((cx + 1) * h_samp).min(real_px_w) {
+                    sum += plane[(y * real_px_w + x) as usize];
+                    count += 1.0;
+                }
+            }
+            component[(cy * coef.rounded_px_w + cx) as usize] = sum / count;
+        }
+    }
+
+    let mut blocks = Vec::with_capacity(coef.block_count as usize);
+    let mut max_distance = 0.0_f32;
+
+    for block_y in 0..coef.block_h {
+        for block_x in 0..coef.block_w {
+            if (block_x + 1) * 8 * h_samp > real_px_w || (block_y + 1) * 8 * v_samp > real_px_h {
+                blocks.push(None);
+                continue;
+            }
+
+            let i = (block_y * coef.block_w + block_x) as usize;
+            let mut block = box_block(&component, coef.rounded_px_w, block_x, block_y);
+            dct8x8s(&mut block);
+
+            let mut violations = 0;
+            for (j, value) in block.iter().enumerate() {
+                let distance = (value / coef.quant_table[j] - coef.dct_coefs[i * 64 + j]).abs();
+                max_distance = max_distance.max(distance);
+                if distance > 0.5 {
+                    violations += 1;
+                }
+            }
+            blocks.push(Some(violations));
+        }
+    }
+
+    ComponentReport {
+        block_w: coef.block_w,
+        block_h: coef.block_h,
+        blocks,
+        max_distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixtures;
+
+    #[test]
+    fn accepts_the_encoded_image() {
+        // Mid grey is 0 in every component, every coefficient is 0
+        let grey = image::RgbImage::from_pixel(20, 12, image::Rgb([128, 128, 128]));
+        let jpeg = fixtures::encode(&grey);
+
+        let report = verify(&grey, JpegSource::Buffer(jpeg)).expect("valid JPEG");
+        assert!(report.is_consistent());
+        assert_eq!(report.components.len(), 3);
+        // 20x12 covers 2x1 whole blocks
+        assert_eq!(report.components[0].checked_blocks(), 2);
+        assert!(report.components[0].max_distance < 0.5);
+    }
+
+    #[test]
+    fn reports_violations() {
+        let grey = image::RgbImage::from_pixel(16, 16, image::Rgb([128, 128, 128]));
+        let jpeg = fixtures::encode(&grey);
+        let black = image::RgbImage::new(16, 16);
+
+        let report = verify(&black, JpegSource::Buffer(jpeg.clone())).expect("valid JPEG");
+        assert!(!report.is_consistent());
+        // Only the DC of the luma blocks moves
+        assert_eq!(report.components[0].violating_blocks(), 4);
+        assert_eq!(report.violations(), 4);
+        assert!(report.components[0].max_distance > 0.5);
+
+        assert!(verify(&image::RgbImage::new(8, 8), JpegSource::Buffer(jpeg)).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    Artefact, ValueCollection,
+    jpeg::{Jpeg, JpegSource},
+    metrics::check_size,
+    planes::Plane,
+    utils::{boxing::box_block, dct::dct8x8s},
+};
+
+/// Plain decode of a JPEG, the starting point of the solver: dequantized
+/// coefficients, inverse DCT and chroma replicated over its sampling
+///
+/// # Errors
+/// Returns an error if reading the JPEG fails.
+pub fn plain_decode(source: JpegSource) -> Result<image::RgbImage, String> {
+    Artefact::default()
+        .iterations(ValueCollection::ForAll(0))
+        .process_source(source)
+}
+
+/// Signed difference between a processed image and its plain decode,
+/// amplified by `gain` around mid grey: brighter where the processing
+/// brightened a channel, darker where it darkened it
+///
+/// # Errors
+/// Returns an error if the images are not of the same size.
+pub fn difference_map(
+    plain: &image::RgbImage,
+    processed: &image::RgbImage,
+    gain: f32,
+) -> Result<image::RgbImage, String> {
+    check_size(plain, processed)?;
+    let mut map = image::RgbImage::new(plain.width(), plain.height());
+    for ((out, a), b) in map.pixels_mut().zip(plain.pixels()).zip(processed.pixels()) {
+        for c in 0..3 {
+            let diff = f32::from(b[c]) - f32::from(a[c]);
+            out[c] = gain.mul_add(diff, 128.0).clamp(0.0, 255.0) as u8;
+        }
+    }
+    Ok(map)
+}
+
+/// Magnitude of the difference between a processed image and its plain
+/// decode in false colours, from black (unchanged) through red and yellow to
+/// white, reached for a difference of `255 / gain` levels
+///
+/// # Errors
+/// Returns an error if the images are not of the same size.
+pub fn heat_map(
+    plain: &image::RgbImage,
+    processed: &image::RgbImage,
+    gain: f32,
+) -> Result<image::RgbImage, String> {
+    check_size(plain, processed)?;
+    let mut map = image::RgbImage::new(plain.width(), plain.height());
+    for ((out, a), b) in map.pixels_mut().zip(plain.pixels()).zip(processed.pixels()) {
+        let norm = (0..3)
+            .map(|c| (f32::from(b[c]) - f32::from(a[c])).powi(2))
+            .sum::<f32>()
+            .sqrt();
+        out.0 = heat_color(norm * gain / 255.0);
+    }
+    Ok(map)
+}
+
+/// How close the coefficients of each block of the reconstruction sit to the
+/// bounds of their quantization interval
+///
+/// In false colours like [`heat_map`]: black for blocks left at the quantized
+/// values, white for blocks pushed to the bounds. Each block shows the mean
+/// over its coefficients of the distance to the quantized value, relative to
+/// half a quantization step. A pixel shows the most pushed of the blocks of
+/// its components. Blocks that extend past the image edge are grey, as their
+/// padding is not part of the planes.
+///
+/// # Arguments
+///
+/// * `jpeg` - The processed JPEG
+/// * `planes` - Its reconstruction at [`crate::PlaneResolution::Native`]
+///
+/// # Errors
+/// Returns an error if the planes do not match the components of the JPEG.
+pub fn bound_proximity_map(jpeg: &Jpeg, planes: &[Plane]) -> Result<image::RgbImage, String> {
+    if planes.len() != jpeg.coefs.len() {
+        return Err(format!(
+            "{} planes for {} components",
+            planes.len(),
+            jpeg.coefs.len()
+        ));
+    }
+
+    let mut proximity = vec![Some(0.0_f32); (jpeg.real_px_w * jpeg.real_px_h) as usize];
+    for (coef, plane) in jpeg.coefs.iter().zip(planes) {
+        let h_samp = coef.horizontal_samp_factor.u32();
+        let v_samp = coef.vertical_samp_factor.u32();
+        if (plane.width, plane.height)
+            != (
+                jpeg.real_px_w.div_ceil(h_samp),
+                jpeg.real_px_h.div_ceil(v_samp),
+            )
+        {
+            return Err("Planes are not at the native resolution of the JPEG".to_string());
+        }
+
+        for block_y in 0..plane.height.div_ceil(8) {
+            for block_x in 0..plane.width.div_ceil(8) {
+                let value = ((block_x + 1) * 8 <= plane.width && (block_y + 1) * 8 <= plane.height)
+                    .then(|| {
+                        let i = (block_y * coef.block_w + block_x) as usize;
+                        let mut block = box_block(&plane.data, plane.width, block_x, block_y);
+                        dct8x8s(&mut block);
+                        block
+                            .iter()
+                            .enumerate()
+                            .map(|(j, value)| {
+                                2.0 * (value / coef.quant_table[j] - coef.dct_coefs[i * 64 + j])
+                                    .abs()
+                            })
+                            .sum::<f32>()
+                            / 64.0
+                    });
+
+                // Pixels of the image covered by the block
+                for y in block_y * 8 * v_samp..((block_y + 1) * 8 * v_samp).min(jpeg.real_px_h) {
+                    for x in block_x * 8 * h_samp..((block_x + 1) * 8 * h_samp).min(jpeg.real_px_w)
+                    {
+                        let pixel = &mut proximity[(y * jpeg.real_px_w + x) as usize];
+                        *pixel = pixel.zip(value).map(|(a, b)| a.max(b));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(image::RgbImage::from_fn(
+        jpeg.real_px_w,
+        jpeg.real_px_h,
+        |x, y| {
+            image::Rgb(proximity[(y * jpeg.real_px_w + x) as usize].map_or([128; 3], heat_color))
+        },
+    ))
+}
+
+/// Black, red, yellow then white as `t` goes from 0 to 1
+fn heat_color(t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    [t, t - 1.0, t - 2.0].map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
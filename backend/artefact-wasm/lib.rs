@@ -1,6 +1,4 @@
-use std::io::Cursor;
-
-use artefact_lib::{Artefact, JpegSource, ValueCollection, image::ImageFormat};
+use artefact_lib::{Artefact, JpegSource, Precision, ValueCollection, encode};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -9,9 +7,12 @@ pub enum OutputFormat {
     Webp,
     Tiff,
     Bmp,
+    Avif,
+    Jxl,
 }
 
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn compute(
     buffer: Vec<u8>,
     output_format: OutputFormat,
@@ -19,27 +20,33 @@ pub fn compute(
     pweight: f32,
     iterations: usize,
     separate_components: bool,
+    half_precision: bool,
+    quality: u8,
+    effort: u8,
 ) -> Result<Vec<u8>, String> {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
     let output_format = match output_format {
-        OutputFormat::Png => ImageFormat::Png,
-        OutputFormat::Webp => ImageFormat::WebP,
-        OutputFormat::Tiff => ImageFormat::Tiff,
-        OutputFormat::Bmp => ImageFormat::Bmp,
+        OutputFormat::Png => artefact_lib::OutputFormat::Png,
+        OutputFormat::Webp => artefact_lib::OutputFormat::Webp,
+        OutputFormat::Tiff => artefact_lib::OutputFormat::Tiff,
+        OutputFormat::Bmp => artefact_lib::OutputFormat::Bmp,
+        OutputFormat::Avif => artefact_lib::OutputFormat::Avif { quality, effort },
+        OutputFormat::Jxl => artefact_lib::OutputFormat::Jxl,
     };
 
-    let mut cursor = Cursor::new(Vec::new());
-
-    Artefact::default()
+    let image = Artefact::default()
         .source(JpegSource::Buffer(buffer))
         .weight(ValueCollection::ForAll(weight))
         .pweight(ValueCollection::ForAll(pweight))
         .iterations(ValueCollection::ForAll(iterations))
         .separate_components(separate_components)
-        .process()?
-        .write_to(&mut cursor, output_format)
-        .map_err(|e| format!("Can't write image to buffer: {e:?}",))?;
+        .precision(if half_precision {
+            Precision::Half
+        } else {
+            Precision::Single
+        })
+        .process()?;
 
-    Ok(cursor.into_inner())
+    encode(&image, output_format)
 }
@@ -127,6 +127,8 @@ pub struct JpegDecoder<T: ZByteReaderTrait> {
     pub(crate) stream: ZReader<T>,
     // Indicate whether headers have been decoded
     pub(crate) headers_decoded: bool,
+    // Indicate whether components have been set up from the headers
+    pub(crate) components_set_up: bool,
     pub(crate) seen_sof: bool,
     // exif data, lifted from app2
     pub(crate) exif_data: Option<Vec<u8>>,
@@ -170,6 +172,7 @@ where
             options: options,
             stream: ZReader::new(buffer),
             headers_decoded: false,
+            components_set_up: false,
             seen_sof: false,
             exif_data: None,
             icc_data: vec![],
@@ -185,8 +188,7 @@ where
     /// # Errors
     /// See DecodeErrors for an explanation
     pub fn decode(&mut self) -> Result<(), DecodeErrors> {
-        self.decode_headers_internal()?;
-        setup_component_params(self)?;
+        self.decode_headers()?;
 
         let mut dct_coefs: [Vec<i16>; MAX_COMPONENTS] = Default::default();
         for (i, comp) in self.components.iter().enumerate() {
@@ -206,6 +208,39 @@ where
         Ok(())
     }
 
+    /// Decode the headers up to the first scan, without decoding the image
+    ///
+    /// Sets up the components, so their quantization tables, sampling factors
+    /// and rounded dimensions are available, along with [`info`], the ICC
+    /// profile and the EXIF data.
+    ///
+    /// # Errors
+    /// See DecodeErrors for an explanation
+    ///
+    /// [`info`]: JpegDecoder::info
+    pub fn decode_headers(&mut self) -> Result<(), DecodeErrors> {
+        self.decode_headers_internal()?;
+        if !self.components_set_up {
+            setup_component_params(self)?;
+            self.components_set_up = true;
+        }
+        Ok(())
+    }
+
+    /// Whether the image is progressive rather than baseline, after the
+    /// headers are decoded
+    #[must_use]
+    pub const fn is_progressive(&self) -> bool {
+        self.is_progressive
+    }
+
+    /// Number of MCUs between restart markers, 0 if there are none, as
+    /// defined before the first scan
+    #[must_use]
+    pub const fn restart_interval(&self) -> usize {
+        self.restart_interval
+    }
+
     /// Create a new Decoder instance
     ///
     /// # Arguments
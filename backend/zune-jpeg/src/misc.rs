@@ -288,7 +288,15 @@ pub(crate) fn setup_component_params<T: ZByteReaderTrait>(
         }
     }
 
-    if img.is_mjpeg {
+    // Motion JPEG frames omit the Huffman tables, and demuxed frames do not
+    // always carry the AVI1 marker, so treat a frame without any as one
+    let has_huffman_tables = img
+        .dc_huffman_tables
+        .iter()
+        .chain(&img.ac_huffman_tables)
+        .any(Option::is_some);
+
+    if img.is_mjpeg || !has_huffman_tables {
         fill_default_mjpeg_tables(
             img.is_progressive,
             &mut img.dc_huffman_tables,
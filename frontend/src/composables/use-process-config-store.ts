@@ -9,7 +9,10 @@ export function useProcessConfigStore(): Ref<ProcessingConfig> {
 		iterations: 50,
 		weight: 0.3,
 		pWeight: 0.001,
-		separateComponents: false
+		separateComponents: false,
+		halfPrecision: false,
+		quality: 90,
+		effort: 7
 	}))
 }
 
@@ -35,7 +38,10 @@ export const processConfigStoreOps = {
 			iterations: 50,
 			weight: 0.3,
 			pWeight: 0.001,
-			separateComponents: false
+			separateComponents: false,
+			halfPrecision: false,
+			quality: 90,
+			effort: 7
 		}
 	},
 
@@ -55,7 +61,10 @@ export const processConfigStoreOps = {
 				processConfigStore.value.iterations === 50 &&
 				processConfigStore.value.weight === 0.3 &&
 				processConfigStore.value.pWeight === 0.001 &&
-				!processConfigStore.value.separateComponents
+				!processConfigStore.value.separateComponents &&
+				!processConfigStore.value.halfPrecision &&
+				processConfigStore.value.quality === 90 &&
+				processConfigStore.value.effort === 7
 			)
 		})
 	},
@@ -85,5 +94,23 @@ export const processConfigStoreOps = {
 			0,
 			1
 		)
+	},
+
+	ensureQualityValid(): void {
+		const processConfigStore = useProcessConfigStore()
+		processConfigStore.value.quality = clamp(
+			processConfigStore.value.quality,
+			1,
+			100
+		)
+	},
+
+	ensureEffortValid(): void {
+		const processConfigStore = useProcessConfigStore()
+		processConfigStore.value.effort = clamp(
+			processConfigStore.value.effort,
+			1,
+			10
+		)
 	}
 }
@@ -38,6 +38,10 @@ self.onmessage = async (event: MessageEvent<WorkerInput>): Promise<void> => {
 						return 2
 					case OutputImgFormat.BMP:
 						return 3
+					case OutputImgFormat.AVIF:
+						return 4
+					case OutputImgFormat.JXL:
+						return 5
 					default:
 						return 0
 				}
@@ -52,7 +56,10 @@ self.onmessage = async (event: MessageEvent<WorkerInput>): Promise<void> => {
 					config.weight,
 					config.pWeight,
 					config.iterations,
-					config.separateComponents
+					config.separateComponents,
+					config.halfPrecision ?? false,
+					config.quality ?? 90,
+					config.effort ?? 7
 				)
 				timer = Date.now() - timer
 
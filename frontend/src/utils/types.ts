@@ -2,7 +2,9 @@ export enum OutputImgFormat {
 	PNG = 'png',
 	WEBP = 'webp',
 	TIF = 'tif',
-	BMP = 'bmp'
+	BMP = 'bmp',
+	AVIF = 'avif',
+	JXL = 'jxl'
 }
 
 export interface ImageItemForDB {
@@ -50,4 +52,10 @@ export interface ProcessingConfig {
 	weight: number
 	pWeight: number
 	separateComponents: boolean
+	/** Store the solver buffers as f16, for images too large for the memory */
+	halfPrecision: boolean
+	/** AVIF quality, 1-100 */
+	quality: number
+	/** AVIF encoder effort, 1-10 */
+	effort: number
 }